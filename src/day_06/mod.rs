@@ -1,3 +1,245 @@
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lifecycle {
+    // days between spawns for an adult fish
+    pub spawn_interval: usize,
+    // extra days a newborn waits before its first spawn cycle
+    pub newborn_delay: usize,
+    // fish reaching this age (in days since birth) die without spawning
+    pub death_age: Option<usize>,
+    // newborns produced per spawn
+    pub litter: usize,
+}
+
+impl Lifecycle {
+    pub const LANTERNFISH: Self = Self {
+        spawn_interval: 7,
+        newborn_delay: 2,
+        death_age: None,
+        litter: 1,
+    };
+
+    fn newborn_timer(&self) -> usize {
+        self.spawn_interval - 1 + self.newborn_delay
+    }
+
+    fn validate(&self, fish: &[usize]) -> Result<(), String> {
+        if self.spawn_interval == 0 {
+            return Err("spawn interval must be at least 1".to_owned());
+        }
+        if let Some(f) = fish.iter().find(|&&f| f > self.newborn_timer()) {
+            return Err(format!(
+                "timer {} exceeds newborn timer {}",
+                f,
+                self.newborn_timer()
+            ));
+        }
+        // with a timer t, a fish is taken to be (newborn timer - t) days old
+        if let Some(death_age) = self.death_age {
+            if let Some(f) = fish
+                .iter()
+                .find(|&&f| self.newborn_timer() - f >= death_age)
+            {
+                return Err(format!(
+                    "a fish with timer {} would be {} days old, past death age {}",
+                    f,
+                    self.newborn_timer() - f,
+                    death_age
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self::LANTERNFISH
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Buckets {
+    // bucket i counts fish whose timer is i
+    Timer,
+    // bucket i counts fish aged i days; used when fish can die
+    Age,
+}
+
+impl Buckets {
+    fn name(&self) -> &'static str {
+        match self {
+            Buckets::Timer => "timer",
+            Buckets::Age => "age",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Simulation {
+    pub buckets: Buckets,
+    // bucket counts for day 0 (the input) through the last simulated day
+    pub days: Vec<Vec<usize>>,
+}
+
+impl Simulation {
+    pub fn total(&self) -> usize {
+        self.days.last().map_or(0, |day| day.iter().sum())
+    }
+
+    pub fn totals(&self) -> Vec<usize> {
+        self.days.iter().map(|day| day.iter().sum()).collect()
+    }
+
+    fn csv_header(&self) -> String {
+        let width = self.days.first().map_or(0, |day| day.len());
+        (0..width)
+            .map(|i| format!("{}_{}", self.buckets.name(), i))
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    fn csv_rows(&self) -> impl Iterator<Item = String> + '_ {
+        self.days.iter().enumerate().map(|(d, day)| {
+            std::iter::once(d.to_string())
+                .chain(day.iter().map(|n| n.to_string()))
+                .collect::<Vec<String>>()
+                .join(",")
+        })
+    }
+
+    pub fn to_csv(&self) -> String {
+        std::iter::once(format!("day,{}", self.csv_header()))
+            .chain(self.csv_rows())
+            .map(|row| row + "\n")
+            .collect()
+    }
+}
+
+fn simulate_timers(fish: &[usize], lifecycle: &Lifecycle, days: usize) -> Vec<Vec<usize>> {
+    let mut buckets = vec![0; lifecycle.newborn_timer() + 1];
+    for &f in fish {
+        buckets[f] += 1;
+    }
+    let mut result = vec![buckets.clone()];
+    for _ in 0..days {
+        let fish_zero = buckets[0];
+        buckets.rotate_left(1);
+        let last = buckets.len() - 1;
+        buckets[last] = 0;
+        buckets[lifecycle.spawn_interval - 1] += fish_zero;
+        buckets[last] += fish_zero * lifecycle.litter;
+        result.push(buckets.clone());
+    }
+    result
+}
+
+fn simulate_ages(
+    fish: &[usize],
+    lifecycle: &Lifecycle,
+    death_age: usize,
+    days: usize,
+) -> Vec<Vec<usize>> {
+    let first_spawn = lifecycle.newborn_timer() + 1;
+    let spawns_at = |age: usize| {
+        age >= first_spawn && (age - first_spawn).is_multiple_of(lifecycle.spawn_interval)
+    };
+    // a fish with timer t is indistinguishable from a newborn of age (newborn timer - t)
+    let mut buckets = vec![0; death_age];
+    for &f in fish {
+        buckets[lifecycle.newborn_timer() - f] += 1;
+    }
+    let mut result = vec![buckets.clone()];
+    for _ in 0..days {
+        if let Some(last) = buckets.last_mut() {
+            *last = 0;
+        }
+        buckets.rotate_right(1);
+        let spawners: usize = (1..buckets.len())
+            .filter(|&age| spawns_at(age))
+            .map(|age| buckets[age])
+            .sum();
+        if let Some(newborns) = buckets.first_mut() {
+            *newborns = spawners * lifecycle.litter;
+        }
+        result.push(buckets.clone());
+    }
+    result
+}
+
+pub fn simulate(fish: &[usize], lifecycle: &Lifecycle, days: usize) -> Result<Simulation, String> {
+    lifecycle.validate(fish)?;
+    Ok(match lifecycle.death_age {
+        None => Simulation {
+            buckets: Buckets::Timer,
+            days: simulate_timers(fish, lifecycle, days),
+        },
+        Some(death_age) => Simulation {
+            buckets: Buckets::Age,
+            days: simulate_ages(fish, lifecycle, death_age, days),
+        },
+    })
+}
+
+// Parses one species per line, formatted like `name: 3,4,3,1,2`.
+pub fn get_species_input(input: &str) -> Result<Vec<(String, Vec<usize>)>, String> {
+    input
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            let (name, fish) = l
+                .split_once(":")
+                .ok_or(format!("missing species name: {}", l))?;
+            let fish = fish
+                .trim()
+                .split(",")
+                .map(|s| s.trim().parse::<usize>().map_err(|e| e.to_string()))
+                .collect::<Result<Vec<usize>, String>>()?;
+            Ok((name.trim().to_owned(), fish))
+        })
+        .collect()
+}
+
+pub fn simulate_species(
+    species: &[(String, Vec<usize>)],
+    lifecycles: &HashMap<String, Lifecycle>,
+    days: usize,
+) -> Result<Vec<(String, Simulation)>, String> {
+    species
+        .iter()
+        .map(|(name, fish)| {
+            let lifecycle = lifecycles
+                .get(name)
+                .ok_or(format!("no lifecycle for species {}", name))?;
+            Ok((name.clone(), simulate(fish, lifecycle, days)?))
+        })
+        .collect()
+}
+
+// Species can have different kinds and numbers of buckets, so each row says which kind it has
+// and shorter rows end in empty cells.
+pub fn species_to_csv(simulations: &[(String, Simulation)]) -> String {
+    let width = simulations
+        .iter()
+        .filter_map(|(_, s)| s.days.first().map(|day| day.len()))
+        .max()
+        .unwrap_or(0);
+    let pad = |row: String, len: usize| row + &",".repeat(width - len);
+    let header = (0..width)
+        .map(|i| format!("bucket_{}", i))
+        .collect::<Vec<String>>()
+        .join(",");
+    std::iter::once(format!("species,buckets,day,{}", header))
+        .chain(simulations.iter().flat_map(|(name, s)| {
+            let len = s.days.first().map_or(0, |day| day.len());
+            s.csv_rows()
+                .map(move |row| pad(format!("{},{},{}", name, s.buckets.name(), row), len))
+        }))
+        .map(|row| row + "\n")
+        .collect()
+}
+
 #[aoc_generator(day6)]
 pub fn get_input(input: &str) -> Vec<usize> {
     input
@@ -6,22 +248,12 @@ pub fn get_input(input: &str) -> Vec<usize> {
         .collect()
 }
 
-fn fish_after_days<'a>(fish: &'a Vec<usize>) -> impl FnOnce(usize) -> usize {
-    let fish = fish.clone();
+fn fish_after_days(fish: &[usize]) -> impl FnOnce(usize) -> usize {
+    let fish = fish.to_vec();
     move |days| {
-        let mut buckets: [usize; 9] = [0; 9];
-        for f in fish {
-            buckets[f] += 1;
-        }
-        for _ in 0..days {
-            let fish_zero = buckets[0];
-            for i in 1..buckets.len() {
-                buckets[i - 1] = buckets[i];
-            }
-            buckets[6] += fish_zero;
-            buckets[8] = fish_zero;
-        }
-        buckets.iter().sum()
+        simulate(&fish, &Lifecycle::LANTERNFISH, days)
+            .unwrap()
+            .total()
     }
 }
 
@@ -50,4 +282,82 @@ mod tests {
     fn test_part_2() {
         assert_eq!(part_2(&get_input("3,4,3,1,2")), 26984457539);
     }
+
+    #[test]
+    fn test_lifecycle() {
+        let fish = get_input("3,4,3,1,2");
+        // an unreachable death age behaves exactly like the timer model
+        let immortal = Lifecycle {
+            death_age: Some(100),
+            ..Lifecycle::LANTERNFISH
+        };
+        assert_eq!(
+            simulate(&fish, &immortal, 80).unwrap().totals(),
+            simulate(&fish, &Lifecycle::LANTERNFISH, 80)
+                .unwrap()
+                .totals()
+        );
+        // a single fish aged 6 that dies at 10 spawns once, on day 3
+        let short_lived = Lifecycle {
+            death_age: Some(10),
+            litter: 2,
+            ..Lifecycle::LANTERNFISH
+        };
+        let s = simulate(&[2], &short_lived, 5).unwrap();
+        assert_eq!(s.totals(), vec![1, 1, 1, 3, 2, 2]);
+        assert!(simulate(&[9], &Lifecycle::LANTERNFISH, 1).is_err());
+        // fish already past their death age are rejected rather than dropped
+        let dies_young = Lifecycle {
+            death_age: Some(5),
+            ..Lifecycle::LANTERNFISH
+        };
+        assert_eq!(
+            simulate(&[0, 3, 8], &dies_young, 2).map(|s| s.totals()),
+            Err("a fish with timer 0 would be 8 days old, past death age 5".to_owned())
+        );
+        // the fish with timer 4 is 4 days old and dies the next day
+        assert_eq!(
+            simulate(&[4, 8], &dies_young, 1).unwrap().totals(),
+            vec![2, 1]
+        );
+    }
+
+    #[test]
+    fn test_species_csv() {
+        let species = get_species_input("lantern: 3,4,3,1,2\nquick: 0\nmayfly: 6").unwrap();
+        let lifecycles = HashMap::from([
+            ("lantern".to_owned(), Lifecycle::LANTERNFISH),
+            (
+                "quick".to_owned(),
+                Lifecycle {
+                    spawn_interval: 1,
+                    newborn_delay: 0,
+                    death_age: None,
+                    litter: 1,
+                },
+            ),
+            (
+                "mayfly".to_owned(),
+                Lifecycle {
+                    death_age: Some(10),
+                    ..Lifecycle::LANTERNFISH
+                },
+            ),
+        ]);
+        let simulations = simulate_species(&species, &lifecycles, 3).unwrap();
+        assert_eq!(simulations[0].1.total(), 7);
+        assert_eq!(simulations[1].1.total(), 8);
+        assert_eq!(
+            simulations[1].1.to_csv(),
+            "day,timer_0\n0,1\n1,2\n2,4\n3,8\n"
+        );
+        let csv = species_to_csv(&simulations);
+        assert!(csv.starts_with("species,buckets,day,bucket_0,"));
+        assert_eq!(csv.lines().count(), 1 + 3 * 4);
+        // shorter rows end in empty cells out to the widest species' buckets
+        assert!(csv.lines().all(|line| line.split(',').count() == 3 + 10));
+        assert!(csv.contains("\nquick,timer,3,8,,,,,,,,,\n"));
+        assert!(csv.contains("\nlantern,timer,0,0,1,1,2,1,0,0,0,0,\n"));
+        assert!(csv.contains("\nmayfly,age,0,0,0,1,0,0,0,0,0,0,0\n"));
+    }
 }
@@ -1,3 +1,293 @@
+pub trait FuelCost {
+    fn cost(&self, distance: usize) -> usize;
+
+    // Whether the cost is convex and nondecreasing in distance, which makes the total cost convex
+    // in the alignment position and lets the optimizer search on its slope.
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+pub struct Linear;
+
+impl FuelCost for Linear {
+    fn cost(&self, distance: usize) -> usize {
+        distance
+    }
+}
+
+pub struct Triangular;
+
+impl FuelCost for Triangular {
+    fn cost(&self, distance: usize) -> usize {
+        triangle_number(distance)
+    }
+}
+
+pub struct Quadratic;
+
+impl FuelCost for Quadratic {
+    fn cost(&self, distance: usize) -> usize {
+        distance * distance
+    }
+}
+
+// Closures are not assumed to be convex; wrap them in `Convex` to opt in to the fast search.
+impl<F: Fn(usize) -> usize> FuelCost for F {
+    fn cost(&self, distance: usize) -> usize {
+        self(distance)
+    }
+
+    fn is_convex(&self) -> bool {
+        false
+    }
+}
+
+pub struct Convex<F: Fn(usize) -> usize>(pub F);
+
+impl<F: Fn(usize) -> usize> FuelCost for Convex<F> {
+    fn cost(&self, distance: usize) -> usize {
+        (self.0)(distance)
+    }
+}
+
+fn distance(a: usize, b: usize) -> usize {
+    a.checked_sub(b).unwrap_or_else(|| b - a)
+}
+
+pub fn total_fuel<C: FuelCost + ?Sized>(crabs: &[usize], cost: &C, pos: usize) -> usize {
+    crabs
+        .iter()
+        .map(|&crab| cost.cost(distance(pos, crab)))
+        .sum()
+}
+
+pub fn total_fuel_weighted<C: FuelCost + ?Sized>(
+    crabs: &[(usize, usize)],
+    cost: &C,
+    pos: usize,
+) -> usize {
+    crabs
+        .iter()
+        .map(|&(crab, count)| count * cost.cost(distance(pos, crab)))
+        .sum()
+}
+
+// Returns the leftmost optimal position and its total cost.
+pub fn optimize<C: FuelCost + ?Sized>(crabs: &[usize], cost: &C) -> Option<(usize, usize)> {
+    optimize_weighted(
+        &crabs.iter().map(|&crab| (crab, 1)).collect::<Vec<_>>(),
+        cost,
+    )
+}
+
+// Like `optimize`, over `(position, count)` pairs.
+pub fn optimize_weighted<C: FuelCost + ?Sized>(
+    crabs: &[(usize, usize)],
+    cost: &C,
+) -> Option<(usize, usize)> {
+    let lo = crabs.iter().map(|&(crab, _)| crab).min()?;
+    let hi = crabs.iter().map(|&(crab, _)| crab).max()?;
+    let total = |pos| total_fuel_weighted(crabs, cost, pos);
+    if !cost.is_convex() {
+        return (lo..=hi)
+            .map(|pos| (pos, total(pos)))
+            .min_by_key(|&(_, t)| t);
+    }
+    // find the first position where the total stops decreasing
+    let (mut lo, mut hi) = (lo, hi);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if total(mid + 1) >= total(mid) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    Some((lo, total(lo)))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alignment {
+    // every position tied for the minimal total, in increasing order
+    pub positions: Vec<usize>,
+    pub total: usize,
+    // (position, total) for every position between the leftmost and rightmost crab
+    pub curve: Vec<(usize, usize)>,
+    // fuel spent by each crab, in input order, when aligning at `positions[0]`
+    pub per_crab: Vec<usize>,
+}
+
+pub fn alignment<C: FuelCost + ?Sized>(crabs: &[usize], cost: &C) -> Option<Alignment> {
+    let lo = *crabs.iter().min()?;
+    let hi = *crabs.iter().max()?;
+    let curve: Vec<(usize, usize)> = (lo..=hi)
+        .map(|pos| (pos, total_fuel(crabs, cost, pos)))
+        .collect();
+    let total = curve.iter().map(|&(_, t)| t).min()?;
+    let positions: Vec<usize> = curve
+        .iter()
+        .filter(|&&(_, t)| t == total)
+        .map(|&(pos, _)| pos)
+        .collect();
+    let per_crab = crabs
+        .iter()
+        .map(|&crab| cost.cost(distance(positions[0], crab)))
+        .collect();
+    Some(Alignment {
+        positions,
+        total,
+        curve,
+        per_crab,
+    })
+}
+
+// The triangular-cost shortcut only checks the two integers around the mean; report whether that
+// actually finds the optimum for these crabs.
+pub fn mean_shortcut_holds(crabs: &[usize]) -> bool {
+    if crabs.is_empty() {
+        return true;
+    }
+    let m = crabs.iter().sum::<usize>().div_euclid(crabs.len());
+    let shortcut = std::cmp::min(
+        total_fuel(crabs, &Triangular, m),
+        total_fuel(crabs, &Triangular, m + 1),
+    );
+    Some(shortcut) == optimize(crabs, &Triangular).map(|(_, total)| total)
+}
+
+// Optimizes each axis independently, which is exact for costs that are a sum of per-axis costs
+// (e.g. `Linear` on every axis for Manhattan distance). Returns the optimal point and its cost.
+pub fn optimize_separable<const N: usize>(
+    crabs: &[([isize; N], usize)],
+    costs: [&dyn FuelCost; N],
+) -> Option<([isize; N], usize)> {
+    let mut point = [0; N];
+    let mut total = 0;
+    for (axis, cost) in costs.iter().enumerate() {
+        let min = crabs.iter().map(|(p, _)| p[axis]).min()?;
+        let shifted: Vec<(usize, usize)> = crabs
+            .iter()
+            .map(|(p, count)| ((p[axis] - min) as usize, *count))
+            .collect();
+        let (pos, fuel) = optimize_weighted(&shifted, *cost)?;
+        point[axis] = min + pos as isize;
+        total += fuel;
+    }
+    Some((point, total))
+}
+
+pub fn optimize_manhattan<const N: usize>(
+    crabs: &[([isize; N], usize)],
+) -> Option<([isize; N], usize)> {
+    optimize_separable(crabs, [&Linear as &dyn FuelCost; N])
+}
+
+fn euclidean<const N: usize>(a: &[f64; N], b: &[f64; N]) -> f64 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f64>()
+        .sqrt()
+}
+
+// Weiszfeld's iteration for the weighted geometric median, with the Vardi-Zhang correction for
+// iterates that land on an input point. Returns the median and the total Euclidean distance.
+pub fn geometric_median<const N: usize>(
+    crabs: &[([isize; N], usize)],
+    tolerance: f64,
+    max_iterations: usize,
+) -> Option<([f64; N], f64)> {
+    let points: Vec<([f64; N], f64)> = crabs
+        .iter()
+        .map(|(p, count)| (p.map(|x| x as f64), *count as f64))
+        .collect();
+    let weight: f64 = points.iter().map(|(_, w)| w).sum();
+    if points.is_empty() || weight == 0.0 {
+        return None;
+    }
+    let mut y = [0.0; N];
+    for (p, w) in points.iter() {
+        for axis in 0..N {
+            y[axis] += p[axis] * w / weight;
+        }
+    }
+    for _ in 0..max_iterations {
+        let mut numerator = [0.0; N];
+        let mut denominator = 0.0;
+        let mut coincident = 0.0;
+        for (p, w) in points.iter() {
+            let d = euclidean(p, &y);
+            if d < f64::EPSILON {
+                coincident += w;
+                continue;
+            }
+            for axis in 0..N {
+                numerator[axis] += w * p[axis] / d;
+            }
+            denominator += w / d;
+        }
+        if denominator == 0.0 {
+            break;
+        }
+        let t = numerator.map(|n| n / denominator);
+        let next = if coincident > 0.0 {
+            let r = euclidean(&t, &y) * denominator;
+            if r <= coincident {
+                break;
+            }
+            let s = coincident / r;
+            let mut next = [0.0; N];
+            for axis in 0..N {
+                next[axis] = (1.0 - s) * t[axis] + s * y[axis];
+            }
+            next
+        } else {
+            t
+        };
+        let moved = euclidean(&next, &y);
+        y = next;
+        if moved < tolerance {
+            break;
+        }
+    }
+    let total = points.iter().map(|(p, w)| w * euclidean(p, &y)).sum();
+    Some((y, total))
+}
+
+// Parses comma-separated crabs, each optionally weighted as `position:count`.
+pub fn get_weighted_input(input: &str) -> Result<Vec<(usize, usize)>, String> {
+    input
+        .trim()
+        .split(",")
+        .map(|s| {
+            let (pos, count) = s.trim().split_once(":").unwrap_or((s.trim(), "1"));
+            Ok((
+                pos.parse::<usize>().map_err(|e| e.to_string())?,
+                count.parse::<usize>().map_err(|e| e.to_string())?,
+            ))
+        })
+        .collect()
+}
+
+// Parses one crab per line as comma-separated coordinates, optionally weighted as `x,y:count`.
+pub fn get_points_input<const N: usize>(input: &str) -> Result<Vec<([isize; N], usize)>, String> {
+    input
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            let (coords, count) = l.trim().split_once(":").unwrap_or((l.trim(), "1"));
+            let coords: [isize; N] = coords
+                .split(",")
+                .map(|s| s.trim().parse::<isize>().map_err(|e| e.to_string()))
+                .collect::<Result<Vec<isize>, String>>()?
+                .try_into()
+                .map_err(|v: Vec<isize>| format!("expected {} coordinates, got {}", N, v.len()))?;
+            Ok((coords, count.parse::<usize>().map_err(|e| e.to_string())?))
+        })
+        .collect()
+}
+
 #[aoc_generator(day7)]
 pub fn get_input(input: &str) -> Vec<usize> {
     input
@@ -8,12 +298,7 @@ pub fn get_input(input: &str) -> Vec<usize> {
 
 #[aoc(day7, part1)]
 pub fn part_1(crabs: &Vec<usize>) -> usize {
-    let mut clone = crabs.clone();
-    let (_, m, _) = clone.select_nth_unstable(crabs.len().div_euclid(2));
-    crabs
-        .iter()
-        .map(|&crab| m.checked_sub(crab).unwrap_or_else(|| crab - *m))
-        .sum()
+    optimize(crabs, &Linear).unwrap().1
 }
 
 fn triangle_number(n: usize) -> usize {
@@ -22,14 +307,7 @@ fn triangle_number(n: usize) -> usize {
 
 #[aoc(day7, part2)]
 pub fn part_2(crabs: &Vec<usize>) -> usize {
-    let total_fuel_to = |pos: usize| -> usize {
-        crabs
-            .iter()
-            .map(|&crab| triangle_number(pos.checked_sub(crab).unwrap_or_else(|| crab - pos)))
-            .sum()
-    };
-    let m = crabs.iter().sum::<usize>().div_euclid(crabs.len());
-    std::cmp::min(total_fuel_to(m), total_fuel_to(m + 1))
+    optimize(crabs, &Triangular).unwrap().1
 }
 
 #[cfg(test)]
@@ -45,4 +323,63 @@ mod tests {
     fn test_part_2() {
         assert_eq!(part_2(&get_input("16,1,2,0,4,2,7,1,2,14")), 168);
     }
+
+    #[test]
+    fn test_fuel_costs() {
+        let crabs = get_input("16,1,2,0,4,2,7,1,2,14");
+        assert_eq!(optimize(&crabs, &Linear), Some((2, 37)));
+        assert_eq!(optimize(&crabs, &Triangular), Some((5, 168)));
+        // the convex search agrees with brute force over closures
+        for cost in [&Linear as &dyn FuelCost, &Triangular, &Quadratic] {
+            let brute = optimize(&crabs, &|d| cost.cost(d));
+            assert_eq!(optimize(&crabs, cost), brute);
+            assert_eq!(optimize(&crabs, &Convex(|d| cost.cost(d))), brute);
+        }
+        // non-convex costs fall back to brute force
+        let step = |d: usize| if d == 0 { 0 } else { 10 };
+        assert_eq!(optimize(&[0, 1, 1, 8, 9], &step), Some((1, 30)));
+        assert_eq!(optimize(&[], &Linear), None);
+    }
+
+    #[test]
+    fn test_alignment() {
+        let crabs = get_input("16,1,2,0,4,2,7,1,2,14");
+        let report = alignment(&crabs, &Triangular).unwrap();
+        assert_eq!(report.positions, vec![5]);
+        assert_eq!(report.total, 168);
+        assert_eq!(report.curve.len(), 17);
+        assert_eq!(report.curve[2], (2, 206));
+        assert_eq!(report.per_crab.iter().sum::<usize>(), 168);
+        assert_eq!(report.per_crab[0], 66);
+        assert!(mean_shortcut_holds(&crabs));
+        let flat = alignment(&[1, 3], &Linear).unwrap();
+        assert_eq!(flat.positions, vec![1, 2, 3]);
+        assert_eq!(flat.per_crab, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_weighted_and_points() {
+        let weighted = get_weighted_input("16,1:2,2:3,0,4,7,14").unwrap();
+        assert_eq!(weighted.iter().map(|&(_, n)| n).sum::<usize>(), 10);
+        assert_eq!(optimize_weighted(&weighted, &Linear), Some((2, 37)));
+        assert_eq!(optimize_weighted(&weighted, &Triangular), Some((5, 168)));
+
+        let points = get_points_input::<2>("0,0\n4,0:2\n0,-3\n4,4").unwrap();
+        assert_eq!(optimize_manhattan(&points), Some(([4, 0], 15)));
+        let costs = [&Linear as &dyn FuelCost, &Triangular];
+        assert_eq!(
+            optimize_separable(&points, costs),
+            Some(([4, 0], 8 + 6 + 10))
+        );
+        assert!(get_points_input::<3>("1,2").is_err());
+
+        // the geometric median of a triangle with a 120° corner is that corner
+        let corner = get_points_input::<2>("0,0:1\n10,0\n-5,8").unwrap();
+        let (median, total) = geometric_median(&corner, 1e-9, 10_000).unwrap();
+        assert!(median[0].abs() < 1e-3 && median[1].abs() < 1e-3);
+        assert!((total - (10.0 + (89.0f64).sqrt())).abs() < 1e-3);
+        let square = get_points_input::<3>("0,0,0\n2,0,0\n0,2,0\n2,2,0").unwrap();
+        let (median, _) = geometric_median(&square, 1e-9, 10_000).unwrap();
+        assert!((median[0] - 1.0).abs() < 1e-6 && (median[1] - 1.0).abs() < 1e-6);
+    }
 }
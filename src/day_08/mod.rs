@@ -1,78 +1,294 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
 pub struct Entry {
-    signal: [Vec<char>; 10],
-    output: [Vec<char>; 4],
+    signal: Vec<Vec<char>>,
+    output: Vec<Vec<char>>,
 }
 
-impl Entry {
-    fn chars_for_one(&self) -> [char; 2] {
-        self.signal
-            .clone()
-            .into_iter()
-            .find(|s| s.len() == 2)
-            .unwrap()
-            .try_into()
-            .unwrap()
+// Segments of each decimal digit, with bit i set for segment 'a' + i:
+//
+//  aaaa
+// b    c
+// b    c
+//  dddd
+// e    f
+// e    f
+//  gggg
+const DIGITS: [u32; 10] = [
+    0b1110111, // 0: abcefg
+    0b0100100, // 1: cf
+    0b1011101, // 2: acdeg
+    0b1101101, // 3: acdfg
+    0b0101110, // 4: bcdf
+    0b1101011, // 5: abdfg
+    0b1111011, // 6: abdefg
+    0b0100101, // 7: acf
+    0b1111111, // 8: abcdefg
+    0b1101111, // 9: abcdfg
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum WiringError {
+    // a pattern uses a wire outside the display
+    InvalidWire(char),
+    // no wire-to-segment permutation explains every signal
+    Inconsistent,
+    // more than one permutation explains every signal
+    Ambiguous,
+    // an output pattern is not a glyph under the derived wiring
+    UnknownOutput(String),
+}
+
+fn pattern_mask(pattern: &[char], n: usize) -> Result<u32, WiringError> {
+    pattern.iter().try_fold(0, |mask, &c| {
+        let i = (c as u32).wrapping_sub('a' as u32) as usize;
+        if i < n {
+            Ok(mask | 1 << i)
+        } else {
+            Err(WiringError::InvalidWire(c))
+        }
+    })
+}
+
+fn bits(mask: u32) -> impl Iterator<Item = usize> {
+    (0..u32::BITS as usize).filter(move |i| mask & 1 << i != 0)
+}
+
+// Finds every permutation mapping wires to segments (both indexed from 0) under which each signal
+// lights up one of the glyphs, giving up once two have been found.
+struct Solver<'a> {
+    glyphs: &'a [u32],
+    signals: Vec<u32>,
+    full: u32,
+}
+
+impl<'a> Solver<'a> {
+    fn new(glyphs: &'a [u32], signals: Vec<u32>, n: usize) -> Self {
+        Solver {
+            glyphs,
+            signals,
+            full: (1 << n) - 1,
+        }
     }
 
-    fn chars_for_four(&self) -> [char; 4] {
-        self.signal
-            .clone()
-            .into_iter()
-            .find(|s| s.len() == 4)
-            .unwrap()
-            .try_into()
-            .unwrap()
+    // Narrows the possible segments for each wire until nothing changes; false on contradiction.
+    fn propagate(&self, domains: &mut [u32]) -> bool {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &signal in self.signals.iter() {
+                let (mut inside, mut outside) = (0, 0);
+                for &glyph in self.glyphs.iter() {
+                    let fits = glyph.count_ones() == signal.count_ones()
+                        && bits(self.full).all(|w| {
+                            let allowed = if signal & 1 << w != 0 {
+                                glyph
+                            } else {
+                                !glyph & self.full
+                            };
+                            domains[w] & allowed != 0
+                        });
+                    if fits {
+                        inside |= glyph;
+                        outside |= !glyph & self.full;
+                    }
+                }
+                for w in bits(self.full) {
+                    let allowed = if signal & 1 << w != 0 {
+                        inside
+                    } else {
+                        outside
+                    };
+                    if domains[w] & !allowed != 0 {
+                        domains[w] &= allowed;
+                        changed = true;
+                    }
+                }
+            }
+            for w in bits(self.full) {
+                if domains[w].count_ones() == 1 {
+                    for v in bits(self.full).filter(|&v| v != w) {
+                        if domains[v] & domains[w] != 0 {
+                            domains[v] &= !domains[w];
+                            changed = true;
+                        }
+                    }
+                }
+            }
+            if domains.contains(&0) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn translate(&self, mask: u32, wiring: &[usize]) -> u32 {
+        bits(mask).fold(0, |acc, w| acc | 1 << wiring[w])
+    }
+
+    fn search(&self, mut domains: Vec<u32>, solutions: &mut Vec<Vec<usize>>) {
+        if solutions.len() > 1 || !self.propagate(&mut domains) {
+            return;
+        }
+        match bits(self.full)
+            .filter(|&w| domains[w].count_ones() > 1)
+            .min_by_key(|&w| domains[w].count_ones())
+        {
+            Some(w) => {
+                for segment in bits(domains[w]) {
+                    let mut next = domains.clone();
+                    next[w] = 1 << segment;
+                    self.search(next, solutions);
+                }
+            }
+            None => {
+                let wiring: Vec<usize> = domains
+                    .iter()
+                    .map(|d| d.trailing_zeros() as usize)
+                    .collect();
+                if self
+                    .signals
+                    .iter()
+                    .all(|&s| self.glyphs.contains(&self.translate(s, &wiring)))
+                {
+                    solutions.push(wiring);
+                }
+            }
+        }
+    }
+
+    fn solve(&self) -> Result<Vec<usize>, WiringError> {
+        let mut solutions = Vec::new();
+        self.search(vec![self.full; bits(self.full).count()], &mut solutions);
+        match solutions.len() {
+            0 => Err(WiringError::Inconsistent),
+            1 => Ok(solutions.pop().unwrap()),
+            _ => Err(WiringError::Ambiguous),
+        }
+    }
+}
+
+fn index_char(i: usize) -> char {
+    (b'a' + i as u8) as char
+}
+
+// Hexadecimal letters as they're usually drawn, with lowercase b and d so they differ from 8 and 0.
+const HEX_LETTERS: [u32; 6] = [
+    0b0111111, // A: abcdef
+    0b1111010, // b: bdefg
+    0b1010011, // C: abeg
+    0b1111100, // d: cdefg
+    0b1011011, // E: abdeg
+    0b0011011, // F: abde
+];
+
+#[derive(Clone, Debug)]
+pub struct SegmentFont {
+    // number of segments, labelled from 'a'
+    segments: usize,
+    labels: Vec<char>,
+    glyphs: Vec<u32>,
+}
+
+impl SegmentFont {
+    // Builds a font from `(label, segments lit)` pairs, e.g. `('1', "cf")`.
+    pub fn new(segments: usize, glyphs: &[(char, &str)]) -> Result<Self, String> {
+        if segments == 0 || segments > 26 {
+            return Err(format!("unsupported segment count: {}", segments));
+        }
+        let mut font = SegmentFont {
+            segments,
+            labels: Vec::new(),
+            glyphs: Vec::new(),
+        };
+        for &(label, pattern) in glyphs {
+            let mask = pattern_mask(&pattern.chars().collect::<Vec<char>>(), segments)
+                .map_err(|e| format!("glyph {}: {:?}", label, e))?;
+            if font.glyphs.contains(&mask) || font.labels.contains(&label) {
+                return Err(format!("duplicate glyph: {}", label));
+            }
+            font.labels.push(label);
+            font.glyphs.push(mask);
+        }
+        Ok(font)
+    }
+
+    pub fn decimal() -> Self {
+        SegmentFont {
+            segments: 7,
+            labels: "0123456789".chars().collect(),
+            glyphs: DIGITS.to_vec(),
+        }
+    }
+
+    pub fn hex() -> Self {
+        SegmentFont {
+            segments: 7,
+            labels: "0123456789AbCdEF".chars().collect(),
+            glyphs: DIGITS.iter().chain(HEX_LETTERS.iter()).cloned().collect(),
+        }
     }
 
-    fn overlap_one(&self, signal: &Vec<char>) -> usize {
-        signal
+    fn glyph(&self, mask: u32) -> Option<usize> {
+        self.glyphs.iter().position(|&g| g == mask)
+    }
+}
+
+impl Entry {
+    fn solver<'a>(&self, font: &'a SegmentFont) -> Result<Solver<'a>, WiringError> {
+        let signals = self
+            .signal
             .iter()
-            .filter(|c| self.chars_for_one().contains(c))
-            .count()
+            .map(|s| pattern_mask(s, font.segments))
+            .collect::<Result<Vec<u32>, WiringError>>()?;
+        Ok(Solver::new(&font.glyphs, signals, font.segments))
     }
 
-    fn overlap_four(&self, signal: &Vec<char>) -> usize {
-        signal
+    // Maps each wire to the segment it actually drives.
+    pub fn wiring(&self, font: &SegmentFont) -> Result<HashMap<char, char>, WiringError> {
+        let wiring = self.solver(font)?.solve()?;
+        Ok(wiring
             .iter()
-            .filter(|c| self.chars_for_four().contains(c))
-            .count()
-    }
-
-    fn value(&self) -> usize {
-        // Of the digits we can't identify immediately (0, 2, 3, 5, 6, and 9), the following table
-        // identifies them based on their number of segments S, and the number of segments where
-        // that digit overlaps with a digit we can immediately identify (1, 4, 7, or 8; except
-        // overlap with 8 is just the number of digits).
-        //
-        // digit | S | 1 | 4 | 7 |
-        // ------+---+---+---+---+
-        //     0 | 6 | 2 | 3 | 3 |
-        //     2 | 5 | 1 | 2 | 2 |
-        //     3 | 5 | 2 | 3 | 3 |
-        //     5 | 5 | 1 | 3 | 2 |
-        //     6 | 6 | 1 | 3 | 2 |
-        //     9 | 6 | 2 | 4 | 3 |
-        //
-        //  No combination of 2 properties is enough to uniquely identify them; nor 1, 4, 7
-        //  properties; nor S, 1, 7. S, 4, 7 or S, 1, 4 are both enough.
+            .enumerate()
+            .map(|(w, &s)| (index_char(w), index_char(s)))
+            .collect())
+    }
+
+    // Indices into the font's glyphs for each output pattern.
+    fn decode_indices(&self, font: &SegmentFont) -> Result<Vec<usize>, WiringError> {
+        let solver = self.solver(font)?;
+        let wiring = solver.solve()?;
         self.output
             .iter()
-            .map(|o| match o.len() {
-                2 => 1,
-                3 => 7,
-                4 => 4,
-                7 => 8,
-                n => match (n, self.overlap_one(o), self.overlap_four(o)) {
-                    (6, 2, 3) => 0,
-                    (5, 1, 2) => 2,
-                    (5, 2, 3) => 3,
-                    (5, 1, 3) => 5,
-                    (6, 1, 3) => 6,
-                    _ => 9,
-                },
+            .map(|o| {
+                font.glyph(solver.translate(pattern_mask(o, font.segments)?, &wiring))
+                    .ok_or_else(|| WiringError::UnknownOutput(o.iter().collect()))
             })
-            .reduce(|sum, n| sum * 10 + n)
-            .unwrap()
+            .collect()
+    }
+
+    // The output as glyph labels, e.g. "C0dE" in the hex font.
+    pub fn decode(&self, font: &SegmentFont) -> Result<String, WiringError> {
+        Ok(self
+            .decode_indices(font)?
+            .into_iter()
+            .map(|i| font.labels[i])
+            .collect())
+    }
+
+    // The output as a number whose digits are glyph indices, in base (number of glyphs).
+    pub fn value_in(&self, font: &SegmentFont) -> Result<usize, WiringError> {
+        Ok(self
+            .decode_indices(font)?
+            .into_iter()
+            .fold(0, |sum, n| sum * font.glyphs.len() + n))
+    }
+
+    pub fn value(&self) -> Result<usize, WiringError> {
+        self.value_in(&SegmentFont::decimal())
     }
 }
 
@@ -83,17 +299,74 @@ impl From<&str> for Entry {
             signal: s_signal
                 .split_whitespace()
                 .map(|s| s.chars().collect())
-                .collect::<Vec<Vec<char>>>()
-                .try_into()
-                .unwrap(),
+                .collect(),
             output: s_output
                 .split_whitespace()
                 .map(|s| s.chars().collect())
-                .collect::<Vec<Vec<char>>>()
-                .try_into()
-                .unwrap(),
+                .collect(),
+        }
+    }
+}
+
+// A random wiring for a display with `segments` segments, mapping each wire to its segment.
+pub fn random_wiring<R: Rng>(segments: usize, rng: &mut R) -> HashMap<char, char> {
+    let mut targets: Vec<usize> = (0..segments).collect();
+    targets.shuffle(rng);
+    targets
+        .into_iter()
+        .enumerate()
+        .map(|(w, s)| (index_char(w), index_char(s)))
+        .collect()
+}
+
+// Produces an entry line showing `value` in `digits` glyphs on a display wired as `wiring` (wire to
+// segment, as returned by `Entry::wiring`), with the signals and their wires in random order.
+pub fn encode<R: Rng>(
+    font: &SegmentFont,
+    value: usize,
+    digits: usize,
+    wiring: &HashMap<char, char>,
+    rng: &mut R,
+) -> Result<String, String> {
+    let mut wire_for = vec![None; font.segments];
+    for (&w, &s) in wiring.iter() {
+        let index = |c: char| (c as usize).wrapping_sub('a' as usize);
+        let (w_i, s_i) = (index(w), index(s));
+        if w_i >= font.segments || s_i >= font.segments || wire_for[s_i].is_some() {
+            return Err(format!("not a permutation of {} wires", font.segments));
         }
+        wire_for[s_i] = Some(w);
     }
+    let wire_for: Vec<char> = wire_for
+        .into_iter()
+        .collect::<Option<Vec<char>>>()
+        .ok_or(format!("not a permutation of {} wires", font.segments))?;
+    let mut scramble = |glyph: u32| -> String {
+        let mut wires: Vec<char> = bits(glyph).map(|s| wire_for[s]).collect();
+        wires.shuffle(rng);
+        wires.into_iter().collect()
+    };
+    let base = font.glyphs.len();
+    let mut output = Vec::new();
+    let mut rest = value;
+    for _ in 0..digits {
+        output.push(scramble(font.glyphs[rest % base]));
+        rest /= base;
+    }
+    if rest != 0 {
+        return Err(format!("{} doesn't fit in {} digits", value, digits));
+    }
+    output.reverse();
+    let mut signals: Vec<String> = font.glyphs.iter().map(|&g| scramble(g)).collect();
+    signals.shuffle(rng);
+    Ok(format!("{} | {}", signals.join(" "), output.join(" ")))
+}
+
+// Encodes a four-digit decimal `value` on a display with a random wiring derived from `seed`.
+pub fn encode_seeded(value: usize, seed: u64) -> Result<String, String> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let wiring = random_wiring(7, &mut rng);
+    encode(&SegmentFont::decimal(), value, 4, &wiring, &mut rng)
 }
 
 #[aoc_generator(day8)]
@@ -119,7 +392,7 @@ pub fn part_1(entries: &Vec<Entry>) -> usize {
 
 #[aoc(day8, part2)]
 pub fn part_2(entries: &Vec<Entry>) -> usize {
-    entries.iter().map(|e| e.value()).sum()
+    entries.iter().map(|e| e.value().unwrap()).sum()
 }
 
 #[cfg(test)]
@@ -137,4 +410,123 @@ mod tests {
     fn test_part_2() {
         assert_eq!(part_2(&get_input(INPUT)), 61229);
     }
+
+    #[test]
+    fn test_wiring() {
+        let entry = Entry::from(
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        );
+        assert_eq!(entry.value(), Ok(5353));
+        let wiring = entry.wiring(&SegmentFont::decimal()).unwrap();
+        assert_eq!(
+            "abcdefg".chars().map(|w| wiring[&w]).collect::<String>(),
+            "cfgabde"
+        );
+        // `ab` can't be both a one and part of a three-segment seven
+        let inconsistent = Entry::from(
+            "acedgfb cdfbe gcdfa fbcad dae cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        );
+        assert_eq!(inconsistent.value(), Err(WiringError::Inconsistent));
+        let unknown = Entry::from(
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdb",
+        );
+        assert_eq!(
+            unknown.value(),
+            Err(WiringError::UnknownOutput("cdb".to_owned()))
+        );
+        assert_eq!(
+            Entry::from("ab ab ab ab ab ab ab ab ab ab | ab ab ab ab").value(),
+            Err(WiringError::Ambiguous)
+        );
+    }
+
+    #[test]
+    fn test_fonts() {
+        // wire a drives segment b, wire b drives segment c, ..., wire g drives segment a
+        let shift = |p: &str| -> String {
+            p.chars()
+                .map(|c| index_char((c as usize - 'a' as usize + 6) % 7))
+                .collect()
+        };
+        let hex = SegmentFont::hex();
+        let signals = [
+            "abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "abdefg", "acf",
+        ]
+        .iter()
+        .chain(
+            [
+                "abcdefg", "abcdfg", "abcdef", "bdefg", "abeg", "cdefg", "abdeg", "abde",
+            ]
+            .iter(),
+        )
+        .map(|p| shift(p))
+        .collect::<Vec<String>>();
+        let outputs = [12, 0, 13, 14].map(|i| signals[i].clone());
+        let entry = Entry::from(format!("{} | {}", signals.join(" "), outputs.join(" ")).as_str());
+        assert_eq!(entry.decode(&hex), Ok("C0dE".to_owned()));
+        assert_eq!(entry.value_in(&hex), Ok(0xC0DE));
+        assert_eq!(entry.wiring(&hex).unwrap()[&'a'], 'b');
+
+        // a 14-segment font where glyph k lights the first k segments
+        let patterns: Vec<String> = (1..=14).map(|k| (0..k).map(index_char).collect()).collect();
+        let glyphs: Vec<(char, &str)> = patterns
+            .iter()
+            .enumerate()
+            .map(|(k, p)| (index_char(k), p.as_str()))
+            .collect();
+        let staircase = SegmentFont::new(14, &glyphs).unwrap();
+        let reversed = |p: &String| -> String {
+            p.chars()
+                .map(|c| index_char(13 - (c as usize - 'a' as usize)))
+                .collect()
+        };
+        let line = format!(
+            "{} | {} {}",
+            patterns
+                .iter()
+                .map(reversed)
+                .collect::<Vec<String>>()
+                .join(" "),
+            reversed(&patterns[2]),
+            reversed(&patterns[0]),
+        );
+        assert_eq!(
+            Entry::from(line.as_str()).decode(&staircase),
+            Ok("ca".to_owned())
+        );
+        assert!(SegmentFont::new(7, &[('1', "cf"), ('7', "fc")]).is_err());
+        assert!(SegmentFont::new(7, &[('x', "ch")]).is_err());
+    }
+
+    #[test]
+    fn test_encode() {
+        use itertools::Itertools;
+
+        let decimal = SegmentFont::decimal();
+        let mut rng = StdRng::seed_from_u64(2021);
+        for (i, segments) in (0..7).permutations(7).enumerate() {
+            let wiring: HashMap<char, char> = segments
+                .into_iter()
+                .enumerate()
+                .map(|(w, s)| (index_char(w), index_char(s)))
+                .collect();
+            let value = (i * 7919) % 10000;
+            let line = encode(&decimal, value, 4, &wiring, &mut rng).unwrap();
+            let entry = Entry::from(line.as_str());
+            assert_eq!(entry.value(), Ok(value));
+            assert_eq!(entry.wiring(&decimal), Ok(wiring));
+        }
+        for seed in 0..20 {
+            let line = encode_seeded(seed as usize * 401, seed).unwrap();
+            assert_eq!(Entry::from(line.as_str()).value(), Ok(seed as usize * 401));
+        }
+        let hex = SegmentFont::hex();
+        let wiring = random_wiring(7, &mut rng);
+        let line = encode(&hex, 0xBEEF, 4, &wiring, &mut rng).unwrap();
+        assert_eq!(
+            Entry::from(line.as_str()).decode(&hex),
+            Ok("bEEF".to_owned())
+        );
+        assert!(encode_seeded(10000, 0).is_err());
+    }
 }
@@ -1,65 +1,305 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 
 pub struct HeightMap {
-    array: Vec<Vec<u8>>,
+    array: Vec<Vec<u32>>,
 }
 
-impl HeightMap {
-    fn get(&self, x: Option<usize>, y: Option<usize>) -> Option<&u8> {
-        match (x, y) {
-            (Some(x), Some(y)) => self.array.get(y).map(|r| r.get(x)).flatten(),
-            _ => None,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    // orthogonal neighbors only
+    Four,
+    // orthogonal and diagonal neighbors
+    Eight,
+}
+
+impl Connectivity {
+    fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Self::Four => &[(-1, 0), (0, -1), (1, 0), (0, 1)],
+            Self::Eight => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Wall {
+    // cells at least this high separate basins
+    AtLeast(u32),
+    Predicate(fn(u32) -> bool),
+}
+
+impl Wall {
+    fn contains(&self, height: u32) -> bool {
+        match self {
+            Self::AtLeast(h) => height >= *h,
+            Self::Predicate(f) => f(height),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    pub connectivity: Connectivity,
+    // strict low points are lower than every neighbor; otherwise a plateau with no lower neighbor
+    // is a single low region
+    pub strict: bool,
+    pub wall: Wall,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            connectivity: Connectivity::Four,
+            strict: true,
+            wall: Wall::AtLeast(9),
+        }
+    }
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        UnionFind {
+            parent: (0..n).collect(),
         }
     }
 
-    fn neighbors(&self, (x, y): (usize, usize)) -> Vec<(usize, usize)> {
-        let mut result = Vec::new();
-        if x > 0 {
-            result.push((x - 1, y));
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
         }
-        if x < self.array[0].len() - 1 {
-            result.push((x + 1, y));
+        let mut i = i;
+        while self.parent[i] != root {
+            let next = self.parent[i];
+            self.parent[i] = root;
+            i = next;
         }
-        if y > 0 {
-            result.push((x, y - 1));
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[a.max(b)] = a.min(b);
         }
-        if y < self.array.len() - 1 {
-            result.push((x, y + 1));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Basin {
+    pub size: usize,
+    // lowest cell, earliest in reading order on ties
+    pub low_point: (usize, usize),
+    // inclusive (x, y) corners
+    pub bounds: ((usize, usize), (usize, usize)),
+    pub mean_height: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WaterFill {
+    // depth of standing water over each cell
+    pub depths: Vec<Vec<u32>>,
+    pub volume: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Basins {
+    // basin index for each cell, or None for walls
+    pub labels: Vec<Vec<Option<usize>>>,
+    // basins ordered by their first cell in reading order
+    pub basins: Vec<Basin>,
+}
+
+impl HeightMap {
+    // Parses multi-digit heights split by `separator`, one row per line.
+    pub fn from_separated(input: &str, separator: char) -> Result<Self, String> {
+        let array = input
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| {
+                l.split(separator)
+                    .map(|s| s.trim().parse::<u32>().map_err(|e| e.to_string()))
+                    .collect::<Result<Vec<u32>, String>>()
+            })
+            .collect::<Result<Vec<Vec<u32>>, String>>()?;
+        if array.windows(2).any(|w| w[0].len() != w[1].len()) {
+            return Err("rows have different lengths".to_owned());
         }
-        result
+        Ok(HeightMap { array })
     }
 
-    fn neighbor_vals(&self, x: usize, y: usize) -> [Option<&u8>; 4] {
-        [
-            self.get(x.checked_sub(1), Some(y)),
-            self.get(Some(x), y.checked_sub(1)),
-            self.get(Some(x), Some(y + 1)),
-            self.get(Some(x + 1), Some(y)),
-        ]
+    fn width(&self) -> usize {
+        self.array.first().map_or(0, |r| r.len())
     }
 
-    fn low_points(&self) -> Vec<(usize, usize)> {
-        self.array
+    fn neighbors(&self, (x, y): (usize, usize), connectivity: Connectivity) -> Vec<(usize, usize)> {
+        connectivity
+            .offsets()
             .iter()
-            .enumerate()
-            .map(|(i, row)| {
-                row.iter()
-                    .enumerate()
-                    .filter_map(|(j, &n)| {
-                        let m: u8 = *self
-                            .neighbor_vals(j, i)
-                            .iter()
-                            .filter_map(|&x| x)
-                            .min()
-                            .unwrap();
-                        if n < m {
-                            Some((j, i))
-                        } else {
-                            None
+            .filter_map(|&(dx, dy)| {
+                let nx = x.checked_add_signed(dx).filter(|&nx| nx < self.width())?;
+                let ny = y
+                    .checked_add_signed(dy)
+                    .filter(|&ny| ny < self.array.len())?;
+                Some((nx, ny))
+            })
+            .collect()
+    }
+
+    // Labels every non-wall cell with its basin in one scan, merging with the already-scanned
+    // neighbors; a basin is a connected region of non-wall cells, however flat.
+    pub fn basins_with(&self, options: &Options) -> Basins {
+        let (h, w) = (self.array.len(), self.width());
+        let is_basin = |(x, y): (usize, usize)| !options.wall.contains(self.array[y][x]);
+        let mut sets = UnionFind::new(w * h);
+        for y in 0..h {
+            for x in (0..w).filter(|&x| is_basin((x, y))) {
+                for (nx, ny) in self.neighbors((x, y), options.connectivity) {
+                    if (ny, nx) < (y, x) && is_basin((nx, ny)) {
+                        sets.union(y * w + x, ny * w + nx);
+                    }
+                }
+            }
+        }
+        let mut labels = vec![vec![None; w]; h];
+        let mut basins: Vec<Basin> = Vec::new();
+        let mut heights: Vec<usize> = Vec::new();
+        let mut root_labels: Vec<Option<usize>> = vec![None; w * h];
+        for (y, row) in self.array.iter().enumerate() {
+            for (x, &height) in row.iter().enumerate().filter(|&(x, _)| is_basin((x, y))) {
+                let root = sets.find(y * w + x);
+                let label = *root_labels[root].get_or_insert_with(|| {
+                    basins.push(Basin {
+                        size: 0,
+                        low_point: (x, y),
+                        bounds: ((x, y), (x, y)),
+                        mean_height: 0.0,
+                    });
+                    heights.push(0);
+                    basins.len() - 1
+                });
+                labels[y][x] = Some(label);
+                let basin = &mut basins[label];
+                basin.size += 1;
+                heights[label] += height as usize;
+                let (lx, ly) = basin.low_point;
+                if height < self.array[ly][lx] {
+                    basin.low_point = (x, y);
+                }
+                let ((x0, y0), (x1, y1)) = basin.bounds;
+                basin.bounds = ((x0.min(x), y0), (x1.max(x), y1.max(y)));
+            }
+        }
+        for (basin, total) in basins.iter_mut().zip(heights) {
+            basin.mean_height = total as f64 / basin.size as f64;
+        }
+        Basins { labels, basins }
+    }
+
+    pub fn basins(&self) -> Basins {
+        self.basins_with(&Options::default())
+    }
+
+    // Low regions in reading order of their first cell; strict low points are single cells.
+    pub fn low_points_with(&self, options: &Options) -> Vec<Vec<(usize, usize)>> {
+        let (h, w) = (self.array.len(), self.width());
+        let height = |(x, y): (usize, usize)| self.array[y][x];
+        // group cells into plateaus of equal height (single cells when strict)
+        let mut sets = UnionFind::new(w * h);
+        if !options.strict {
+            for y in 0..h {
+                for x in 0..w {
+                    for (nx, ny) in self.neighbors((x, y), options.connectivity) {
+                        if height((nx, ny)) == height((x, y)) {
+                            sets.union(y * w + x, ny * w + nx);
                         }
-                    })
-                    .collect::<Vec<(usize, usize)>>()
+                    }
+                }
+            }
+        }
+        let mut is_low = vec![true; w * h];
+        let mut regions: Vec<Vec<(usize, usize)>> = Vec::new();
+        let mut root_regions: Vec<Option<usize>> = vec![None; w * h];
+        for y in 0..h {
+            for x in 0..w {
+                let root = sets.find(y * w + x);
+                let blocked = options.wall.contains(height((x, y)))
+                    || self
+                        .neighbors((x, y), options.connectivity)
+                        .into_iter()
+                        .any(|n| {
+                            height(n) < height((x, y))
+                                || (options.strict && height(n) == height((x, y)))
+                        });
+                if blocked {
+                    is_low[root] = false;
+                }
+                let region = *root_regions[root].get_or_insert_with(|| {
+                    regions.push(Vec::new());
+                    regions.len() - 1
+                });
+                regions[region].push((x, y));
+            }
+        }
+        regions
+            .into_iter()
+            .filter(|r| {
+                let (x, y) = r[0];
+                is_low[sets.find(y * w + x)]
             })
+            .collect()
+    }
+
+    // Fills the map with water that drains off the edges, flooding inwards from the border in order
+    // of height: each cell holds water up to the lowest level it can spill over to reach an edge.
+    pub fn water_fill(&self, connectivity: Connectivity) -> WaterFill {
+        let (h, w) = (self.array.len(), self.width());
+        let mut levels = self.array.clone();
+        let mut visited = vec![vec![false; w]; h];
+        let mut queue = BinaryHeap::new();
+        for (y, row) in visited.iter_mut().enumerate() {
+            for (x, seen) in row.iter_mut().enumerate() {
+                if x == 0 || y == 0 || x == w - 1 || y == h - 1 {
+                    *seen = true;
+                    queue.push(Reverse((self.array[y][x], x, y)));
+                }
+            }
+        }
+        while let Some(Reverse((level, x, y))) = queue.pop() {
+            for (nx, ny) in self.neighbors((x, y), connectivity) {
+                if !visited[ny][nx] {
+                    visited[ny][nx] = true;
+                    levels[ny][nx] = level.max(self.array[ny][nx]);
+                    queue.push(Reverse((levels[ny][nx], nx, ny)));
+                }
+            }
+        }
+        let depths: Vec<Vec<u32>> = levels
+            .iter()
+            .zip(self.array.iter())
+            .map(|(l, a)| l.iter().zip(a.iter()).map(|(l, a)| l - a).collect())
+            .collect();
+        let volume = depths.iter().flatten().map(|&d| d as u64).sum();
+        WaterFill { depths, volume }
+    }
+
+    fn low_points(&self) -> Vec<(usize, usize)> {
+        self.low_points_with(&Options::default())
+            .into_iter()
             .flatten()
             .collect()
     }
@@ -70,7 +310,7 @@ pub fn get_input(input: &str) -> HeightMap {
     HeightMap {
         array: input
             .lines()
-            .map(|l| l.chars().map(|c| c.to_digit(10).unwrap() as u8).collect())
+            .map(|l| l.chars().map(|c| c.to_digit(10).unwrap()).collect())
             .collect(),
     }
 }
@@ -86,25 +326,9 @@ pub fn part_1(entries: &HeightMap) -> usize {
 
 #[aoc(day9, part2)]
 pub fn part_2(entries: &HeightMap) -> usize {
-    let mut basins: HashMap<(usize, usize), HashSet<(usize, usize)>> = HashMap::new();
-    for low in entries.low_points() {
-        let mut queue: VecDeque<(usize, usize)> = VecDeque::from([low]);
-        let mut set: HashSet<(usize, usize)> = HashSet::from([low]);
-        while let Some(queue_item) = queue.pop_front() {
-            for neighbor in entries.neighbors(queue_item).iter() {
-                let neighbor_val = entries.array[neighbor.1][neighbor.0];
-                let prev_val = entries.array[queue_item.1][queue_item.0];
-                if !set.contains(neighbor) && prev_val < neighbor_val && neighbor_val < 9 {
-                    queue.push_back(*neighbor);
-                    set.insert(*neighbor);
-                }
-            }
-        }
-        basins.entry(low).or_insert(set);
-    }
-    let mut basin_counts: Vec<usize> = basins.values().map(|points| points.len()).collect();
+    let mut basin_counts: Vec<usize> = entries.basins().basins.iter().map(|b| b.size).collect();
     basin_counts.sort();
-    basin_counts.iter().rev().take(3).fold(1, |sum, n| sum * n)
+    basin_counts.iter().rev().take(3).product()
 }
 
 #[cfg(test)]
@@ -122,4 +346,82 @@ mod tests {
     fn test_part_2() {
         assert_eq!(part_2(&get_input(INPUT)), 1134);
     }
+
+    #[test]
+    fn test_basins() {
+        let basins = get_input(INPUT).basins();
+        assert_eq!(basins.basins.len(), 4);
+        assert_eq!(
+            basins.basins[0],
+            Basin {
+                size: 3,
+                low_point: (1, 0),
+                bounds: ((0, 0), (1, 1)),
+                mean_height: 2.0,
+            }
+        );
+        assert_eq!(basins.labels[0][0], Some(0));
+        assert_eq!(basins.labels[0][2], None);
+        assert_eq!(basins.basins[1].low_point, (9, 0));
+        // a plateau with no strict low point is still one basin
+        let flat = get_input("5559\n5959\n5555");
+        let basins = flat.basins();
+        assert_eq!(basins.basins.len(), 1);
+        assert_eq!(basins.basins[0].size, 9);
+        assert_eq!(basins.basins[0].low_point, (0, 0));
+        assert!(flat.low_points().is_empty());
+    }
+
+    #[test]
+    fn test_options() {
+        let map = HeightMap::from_separated("10 12 30\n12 12 30\n30 30 11", ' ').unwrap();
+        let walls = Options {
+            wall: Wall::AtLeast(30),
+            ..Options::default()
+        };
+        assert_eq!(
+            map.low_points_with(&walls),
+            vec![vec![(0, 0)], vec![(2, 2)]]
+        );
+        let plateaus = Options {
+            strict: false,
+            wall: Wall::AtLeast(30),
+            ..Options::default()
+        };
+        let flat = HeightMap::from_separated("5,5,7\n5,7,7\n7,7,6", ',').unwrap();
+        assert_eq!(flat.low_points(), vec![(2, 2)]);
+        assert_eq!(
+            flat.low_points_with(&plateaus),
+            vec![vec![(0, 0), (1, 0), (0, 1)], vec![(2, 2)]]
+        );
+        // diagonal neighbors join the two corners of the map into one basin
+        let diagonal = Options {
+            connectivity: Connectivity::Eight,
+            wall: Wall::AtLeast(30),
+            ..Options::default()
+        };
+        assert_eq!(map.basins_with(&walls).basins.len(), 2);
+        let basins = map.basins_with(&diagonal);
+        assert_eq!(basins.basins.len(), 1);
+        assert_eq!(basins.basins[0].size, 5);
+        assert_eq!(basins.basins[0].low_point, (0, 0));
+        let predicate = Options {
+            wall: Wall::Predicate(|h| h == 12 || h == 30),
+            ..Options::default()
+        };
+        assert_eq!(map.basins_with(&predicate).basins.len(), 2);
+        assert!(HeightMap::from_separated("1 2\n3", ' ').is_err());
+    }
+
+    #[test]
+    fn test_water_fill() {
+        let map = HeightMap::from_separated("1 4 3 1 3 2\n3 2 1 3 2 4\n2 3 3 2 3 1", ' ').unwrap();
+        let fill = map.water_fill(Connectivity::Four);
+        assert_eq!(fill.volume, 4);
+        assert_eq!(fill.depths[1], vec![0, 1, 2, 0, 1, 0]);
+        // water leaks out of a ring through its diagonal gaps only with 8-connectivity
+        let ring = get_input("09990\n90009\n90309\n90009\n09990");
+        assert_eq!(ring.water_fill(Connectivity::Four).volume, 9 * 9 - 3);
+        assert_eq!(ring.water_fill(Connectivity::Eight).volume, 0);
+    }
 }
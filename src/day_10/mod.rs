@@ -1,127 +1,456 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+
 #[derive(Debug)]
 pub enum Bracket {
     Open(char),
+    // holds the opener it closes
     Close(char),
 }
 
-enum Error {
-    Corrupted(usize),
-    Incomplete(usize),
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Diagnostic {
+    // a closer that doesn't match the innermost open bracket
+    Corrupted {
+        offset: usize,
+        found: char,
+        expected: char,
+    },
+    // a closer with no open bracket left for it to close
+    Unopened {
+        offset: usize,
+        found: char,
+    },
+    // the line ends with brackets still open; appending `completion` closes them
+    Incomplete {
+        completion: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Located {
+    // 1-based line and character column; incomplete lines point just past their last character
+    pub line: usize,
+    pub column: usize,
+    pub diagnostic: Diagnostic,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub lines: usize,
+    // corrupted lines, including ones with unopened closers
+    pub corrupted: usize,
+    pub incomplete: usize,
+    // the middle completion score (lower middle for an even count)
+    pub median_completion: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Edit {
+    // insert `c` before the character at byte `offset` (or at the end of the line)
+    Insert { offset: usize, c: char },
+    Delete { offset: usize, c: char },
+    Substitute { offset: usize, from: char, to: char },
 }
 
-impl Into<usize> for Error {
-    fn into(self) -> usize {
+impl Edit {
+    fn offset(&self) -> usize {
         match self {
-            Self::Corrupted(n) => n,
-            Self::Incomplete(n) => n,
+            Self::Insert { offset, .. }
+            | Self::Delete { offset, .. }
+            | Self::Substitute { offset, .. } => *offset,
         }
     }
 }
 
-impl Bracket {
-    fn score_corrupted(&self) -> usize {
-        let score = |&bracket| match bracket {
-            '(' => 3,
-            '[' => 57,
-            '{' => 1197,
-            '<' => 25137,
-            _ => panic!("wrong bracket char"),
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repair {
+    // ordered by offset, insertions before other edits at the same offset
+    pub edits: Vec<Edit>,
+    pub line: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct BracketSet {
+    // opener to closer; a pair like `"` that closes itself opens when it isn't the innermost opener
+    closers: HashMap<char, char>,
+    openers: HashMap<char, char>,
+    // scores keyed on the closer
+    corrupted_scores: HashMap<char, usize>,
+    incomplete_scores: HashMap<char, usize>,
+    ignore_other: bool,
+}
+
+impl BracketSet {
+    pub fn new(pairs: &[(char, char)]) -> Result<Self, String> {
+        let mut set = BracketSet {
+            closers: HashMap::new(),
+            openers: HashMap::new(),
+            corrupted_scores: HashMap::new(),
+            incomplete_scores: HashMap::new(),
+            ignore_other: false,
         };
-        match self {
-            Self::Open(bracket) => score(bracket),
-            Self::Close(bracket) => score(bracket),
+        for &(open, close) in pairs {
+            let taken = |c| set.closers.contains_key(&c) || set.openers.contains_key(&c);
+            if taken(open) || taken(close) {
+                return Err(format!("bracket used twice: {}{}", open, close));
+            }
+            set.closers.insert(open, close);
+            set.openers.insert(close, open);
         }
+        Ok(set)
+    }
+
+    pub fn with_scores(
+        mut self,
+        corrupted: &[(char, usize)],
+        incomplete: &[(char, usize)],
+    ) -> Self {
+        self.corrupted_scores = corrupted.iter().cloned().collect();
+        self.incomplete_scores = incomplete.iter().cloned().collect();
+        self
     }
 
-    fn score_incomplete(&self) -> usize {
-        let score = |&bracket| match bracket {
-            '(' => 1,
-            '[' => 2,
-            '{' => 3,
-            '<' => 4,
-            _ => panic!("wrong bracket char"),
+    // Skip characters that aren't brackets instead of rejecting the line.
+    pub fn ignoring_other(mut self) -> Self {
+        self.ignore_other = true;
+        self
+    }
+
+    fn classify(&self, c: char, innermost: Option<&char>) -> Option<Bracket> {
+        match (self.closers.get(&c), self.openers.get(&c)) {
+            (Some(&close), _) if close == c && innermost != Some(&c) => Some(Bracket::Open(c)),
+            (_, Some(&open)) => Some(Bracket::Close(open)),
+            (Some(_), None) => Some(Bracket::Open(c)),
+            (None, None) => None,
+        }
+    }
+
+    // Feeds one character through the stack of open brackets, returning a diagnostic if it can't
+    // be closed there.
+    fn push(
+        &self,
+        stack: &mut Vec<char>,
+        offset: usize,
+        c: char,
+    ) -> Result<Option<Diagnostic>, String> {
+        match self.classify(c, stack.last()) {
+            Some(Bracket::Open(open)) => stack.push(open),
+            Some(Bracket::Close(close)) => match stack.pop() {
+                Some(open) if open == close => {}
+                Some(open) => {
+                    return Ok(Some(Diagnostic::Corrupted {
+                        offset,
+                        found: c,
+                        expected: self.closers[&open],
+                    }))
+                }
+                None => return Ok(Some(Diagnostic::Unopened { offset, found: c })),
+            },
+            None if self.ignore_other => {}
+            None => return Err(format!("not a valid bracket: {}", c)),
+        }
+        Ok(None)
+    }
+
+    fn completion(&self, stack: &[char]) -> String {
+        stack.iter().rev().map(|open| self.closers[open]).collect()
+    }
+
+    // Checks a line, with byte offsets into it; balanced lines have no diagnostic.
+    pub fn diagnose(&self, line: &str) -> Result<Option<Diagnostic>, String> {
+        let mut stack: Vec<char> = Vec::new();
+        for (offset, c) in line.char_indices() {
+            if let Some(diagnostic) = self.push(&mut stack, offset, c)? {
+                return Ok(Some(diagnostic));
+            }
+        }
+        Ok((!stack.is_empty()).then(|| Diagnostic::Incomplete {
+            completion: self.completion(&stack),
+        }))
+    }
+
+    // Checks input a line at a time. With `carry`, brackets left open at the end of a line stay
+    // open into the next one and only the end of the input is checked for completeness; after a
+    // corruption the open brackets are dropped and checking resumes on the next line.
+    pub fn check_reader<R: BufRead>(
+        &self,
+        mut reader: R,
+        carry: bool,
+    ) -> Result<(Vec<Located>, Summary), String> {
+        let mut buffer = String::new();
+        let mut stack: Vec<char> = Vec::new();
+        let mut located: Vec<Located> = Vec::new();
+        let mut scores: Vec<usize> = Vec::new();
+        let mut summary = Summary::default();
+        let mut end = (0, 1);
+        let mut incomplete = |stack: &mut Vec<char>, (line, column), located: &mut Vec<Located>| {
+            let diagnostic = Diagnostic::Incomplete {
+                completion: self.completion(stack),
+            };
+            scores.extend(self.score_incomplete(&diagnostic));
+            located.push(Located {
+                line,
+                column,
+                diagnostic,
+            });
+            stack.clear();
         };
-        match self {
-            Self::Open(bracket) => score(bracket),
-            Self::Close(bracket) => score(bracket),
+        loop {
+            buffer.clear();
+            if reader.read_line(&mut buffer).map_err(|e| e.to_string())? == 0 {
+                break;
+            }
+            summary.lines += 1;
+            let line = buffer.trim_end_matches(['\n', '\r']);
+            for (column, (offset, c)) in line.char_indices().enumerate() {
+                let diagnostic = self
+                    .push(&mut stack, offset, c)
+                    .map_err(|e| format!("line {}: {}", summary.lines, e))?;
+                if let Some(diagnostic) = diagnostic {
+                    located.push(Located {
+                        line: summary.lines,
+                        column: column + 1,
+                        diagnostic,
+                    });
+                    stack.clear();
+                    break;
+                }
+            }
+            end = (summary.lines, line.chars().count() + 1);
+            if !carry && !stack.is_empty() {
+                incomplete(&mut stack, end, &mut located);
+            }
+        }
+        if !stack.is_empty() {
+            incomplete(&mut stack, end, &mut located);
         }
+        for l in located.iter() {
+            match l.diagnostic {
+                Diagnostic::Incomplete { .. } => summary.incomplete += 1,
+                _ => summary.corrupted += 1,
+            }
+        }
+        summary.median_completion = median(scores);
+        Ok((located, summary))
     }
-}
 
-impl TryFrom<char> for Bracket {
-    type Error = String;
+    pub fn score_corrupted(&self, diagnostic: &Diagnostic) -> Option<usize> {
+        match diagnostic {
+            Diagnostic::Corrupted { found, .. } | Diagnostic::Unopened { found, .. } => {
+                self.corrupted_scores.get(found).cloned()
+            }
+            Diagnostic::Incomplete { .. } => None,
+        }
+    }
 
-    fn try_from(c: char) -> Result<Self, Self::Error> {
-        match c {
-            '(' => Ok(Bracket::Open('(')),
-            '[' => Ok(Bracket::Open('[')),
-            '{' => Ok(Bracket::Open('{')),
-            '<' => Ok(Bracket::Open('<')),
-            ')' => Ok(Bracket::Close('(')),
-            ']' => Ok(Bracket::Close('[')),
-            '}' => Ok(Bracket::Close('{')),
-            '>' => Ok(Bracket::Close('<')),
-            _ => Err(format!("not a valid bracket: {}", c)),
+    pub fn score_incomplete(&self, diagnostic: &Diagnostic) -> Option<usize> {
+        match diagnostic {
+            Diagnostic::Incomplete { completion } => {
+                completion.chars().try_fold(0usize, |sum, c| {
+                    sum.checked_mul(5)?
+                        .checked_add(*self.incomplete_scores.get(&c)?)
+                })
+            }
+            _ => None,
         }
     }
 }
 
-fn error_score(brackets: &Vec<Bracket>) -> Error {
-    let mut stack: Vec<&Bracket> = Vec::new();
-    for b in brackets {
-        match b {
-            Bracket::Open(_) => {
-                stack.push(b);
+// Interval DP over the brackets of a line: `costs[i][j]` is the fewest edits that balance brackets
+// `i..j`, where bracket `i` is either left unmatched (one insertion or deletion) or paired with some
+// bracket `k`, costing a substitution for each of the two that doesn't fit the pair.
+struct Repairer<'a> {
+    set: &'a BracketSet,
+    brackets: Vec<(usize, char)>,
+    end: usize,
+    costs: Vec<Vec<usize>>,
+}
+
+impl<'a> Repairer<'a> {
+    fn new(set: &'a BracketSet, line: &str) -> Result<Self, String> {
+        let mut brackets = Vec::new();
+        for (offset, c) in line.char_indices() {
+            if set.closers.contains_key(&c) || set.openers.contains_key(&c) {
+                brackets.push((offset, c));
+            } else if !set.ignore_other {
+                return Err(format!("not a valid bracket: {}", c));
             }
-            Bracket::Close(close) => {
-                if let Some(Bracket::Open(open)) = stack.pop() {
-                    if open != close {
-                        return Error::Corrupted(b.score_corrupted());
-                    }
-                } else {
-                    return Error::Corrupted(b.score_corrupted());
-                }
+        }
+        let n = brackets.len();
+        let mut repairer = Repairer {
+            set,
+            brackets,
+            end: line.len(),
+            costs: vec![vec![0; n + 1]; n + 1],
+        };
+        for len in 1..=n {
+            for i in 0..=n - len {
+                let j = i + len;
+                repairer.costs[i][j] = (i + 1..j)
+                    .map(|k| repairer.paired_cost(i, k, j))
+                    .chain(std::iter::once(1 + repairer.costs[i + 1][j]))
+                    .min()
+                    .unwrap();
             }
         }
+        Ok(repairer)
+    }
+
+    // The opener/closer pair that brackets `a` and `b` can become with the fewest substitutions.
+    fn pair_for(&self, a: char, b: char) -> (char, char) {
+        match (self.set.closers.get(&a), self.set.openers.get(&b)) {
+            (Some(&close), _) if close == b => (a, b),
+            (Some(&close), _) => (a, close),
+            (None, Some(&open)) => (open, b),
+            (None, None) => self
+                .set
+                .closers
+                .iter()
+                .map(|(&open, &close)| (open, close))
+                .min()
+                .unwrap(),
+        }
+    }
+
+    fn paired_cost(&self, i: usize, k: usize, j: usize) -> usize {
+        let (a, b) = (self.brackets[i].1, self.brackets[k].1);
+        let (open, close) = self.pair_for(a, b);
+        (open != a) as usize + (close != b) as usize + self.costs[i + 1][k] + self.costs[k + 1][j]
+    }
+
+    fn offset(&self, i: usize) -> usize {
+        self.brackets.get(i).map_or(self.end, |&(offset, _)| offset)
     }
-    Error::Incomplete(
-        stack
-            .iter()
+
+    fn edits(&self, i: usize, j: usize, edits: &mut Vec<Edit>) {
+        if i == j {
+            return;
+        }
+        let (offset, a) = self.brackets[i];
+        // prefer the outermost partner, so ties nest rather than split into siblings
+        if let Some(k) = (i + 1..j)
             .rev()
-            .fold(0, |sum, b| 5 * sum + b.score_incomplete()),
-    )
+            .find(|&k| self.paired_cost(i, k, j) == self.costs[i][j])
+        {
+            let (open, close) = self.pair_for(a, self.brackets[k].1);
+            if open != a {
+                edits.push(Edit::Substitute {
+                    offset,
+                    from: a,
+                    to: open,
+                });
+            }
+            self.edits(i + 1, k, edits);
+            let (offset_k, b) = self.brackets[k];
+            if close != b {
+                edits.push(Edit::Substitute {
+                    offset: offset_k,
+                    from: b,
+                    to: close,
+                });
+            }
+            self.edits(k + 1, j, edits);
+        } else if let Some(&close) = self.set.closers.get(&a) {
+            // close an unmatched opener at the end of its span
+            self.edits(i + 1, j, edits);
+            edits.push(Edit::Insert {
+                offset: self.offset(j),
+                c: close,
+            });
+        } else {
+            edits.push(Edit::Delete { offset, c: a });
+            self.edits(i + 1, j, edits);
+        }
+    }
+}
+
+impl BracketSet {
+    // The fewest insertions, deletions and substitutions that balance a line.
+    pub fn repair(&self, line: &str) -> Result<Repair, String> {
+        let repairer = Repairer::new(self, line)?;
+        let mut edits = Vec::new();
+        repairer.edits(0, repairer.brackets.len(), &mut edits);
+        edits.sort_by_key(|e| (e.offset(), !matches!(e, Edit::Insert { .. })));
+        let mut repaired = String::new();
+        let mut pending = edits.iter().peekable();
+        for (offset, c) in line
+            .char_indices()
+            .chain(std::iter::once((line.len(), '\0')))
+        {
+            while let Some(Edit::Insert { c, .. }) =
+                pending.next_if(|e| e.offset() == offset && matches!(e, Edit::Insert { .. }))
+            {
+                repaired.push(*c);
+            }
+            if offset == line.len() {
+                break;
+            }
+            match pending.next_if(|e| e.offset() == offset) {
+                Some(Edit::Substitute { to, .. }) => repaired.push(*to),
+                Some(_) => {}
+                None => repaired.push(c),
+            }
+        }
+        Ok(Repair {
+            edits,
+            line: repaired,
+        })
+    }
+}
+
+impl Default for BracketSet {
+    fn default() -> Self {
+        BracketSet::new(&[('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')])
+            .unwrap()
+            .with_scores(
+                &[(')', 3), (']', 57), ('}', 1197), ('>', 25137)],
+                &[(')', 1), (']', 2), ('}', 3), ('>', 4)],
+            )
+    }
 }
 
 #[aoc_generator(day10)]
-pub fn get_input(input: &str) -> Vec<Vec<Bracket>> {
+pub fn get_input(input: &str) -> Result<Vec<Diagnostic>, String> {
+    let brackets = BracketSet::default();
     input
         .lines()
-        .map(|l| l.chars().map(|c| c.try_into().unwrap()).collect())
+        .enumerate()
+        .filter_map(|(i, l)| {
+            brackets
+                .diagnose(l)
+                .map_err(|e| format!("line {}: {}", i + 1, e))
+                .transpose()
+        })
         .collect()
 }
 
 #[aoc(day10, part1)]
-pub fn part_1(bracket_lines: &Vec<Vec<Bracket>>) -> usize {
-    bracket_lines
+pub fn part_1(diagnostics: &[Diagnostic]) -> usize {
+    let brackets = BracketSet::default();
+    diagnostics
         .iter()
-        .filter_map(|l| match error_score(l) {
-            Error::Corrupted(score) => Some(score),
-            _ => None,
-        })
+        .filter_map(|d| brackets.score_corrupted(d))
         .sum()
 }
 
+// The middle score, or the lower middle for an even count.
+pub fn median(mut scores: Vec<usize>) -> Option<usize> {
+    scores.sort();
+    match scores.len() {
+        0 => None,
+        n => Some(scores[(n - 1) / 2]),
+    }
+}
+
 #[aoc(day10, part2)]
-pub fn part_2(bracket_lines: &Vec<Vec<Bracket>>) -> usize {
-    let mut scores: Vec<usize> = bracket_lines
+pub fn part_2(diagnostics: &[Diagnostic]) -> Result<usize, String> {
+    let brackets = BracketSet::default();
+    let scores: Vec<usize> = diagnostics
         .iter()
-        .filter_map(|l| match error_score(l) {
-            Error::Incomplete(score) => Some(score),
-            _ => None,
-        })
+        .filter_map(|d| brackets.score_incomplete(d))
         .collect();
-    scores.sort();
-    scores[scores.len().div_euclid(2)]
+    median(scores).ok_or("no incomplete lines".to_owned())
 }
 
 #[cfg(test)]
@@ -132,11 +461,221 @@ mod tests {
 
     #[test]
     fn test_part_1() {
-        assert_eq!(part_1(&get_input(INPUT)), 26397);
+        assert_eq!(part_1(&get_input(INPUT).unwrap()), 26397);
+        assert_eq!(
+            get_input("()\n(a)"),
+            Err("line 2: not a valid bracket: a".to_owned())
+        );
     }
 
     #[test]
     fn test_part_2() {
-        assert_eq!(part_2(&get_input(INPUT)), 288957);
+        assert_eq!(part_2(&get_input(INPUT).unwrap()), Ok(288957));
+        assert_eq!(part_2(&get_input("()\n(\n[\n{\n<").unwrap()), Ok(2));
+        assert!(part_2(&get_input("()").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let brackets = BracketSet::default();
+        assert_eq!(
+            brackets.diagnose("{([(<{}[<>[]}>{[]{[(<()>"),
+            Ok(Some(Diagnostic::Corrupted {
+                offset: 12,
+                found: '}',
+                expected: ']',
+            }))
+        );
+        assert_eq!(
+            brackets.diagnose("[({(<(())[]>[[{[]{<()<>>"),
+            Ok(Some(Diagnostic::Incomplete {
+                completion: "}}]])})]".to_owned()
+            }))
+        );
+        assert_eq!(
+            brackets.diagnose("()>"),
+            Ok(Some(Diagnostic::Unopened {
+                offset: 2,
+                found: '>'
+            }))
+        );
+        assert_eq!(brackets.diagnose("<[]>"), Ok(None));
+        assert!(brackets.diagnose("(x)").is_err());
+        let incomplete = brackets
+            .diagnose("<{([{{}}[<[[[<>{}]]]>[]]")
+            .unwrap()
+            .unwrap();
+        assert_eq!(brackets.score_incomplete(&incomplete), Some(294));
+        assert_eq!(brackets.score_corrupted(&incomplete), None);
+    }
+
+    #[test]
+    fn test_bracket_set() {
+        let quotes = BracketSet::new(&[('«', '»'), ('"', '"'), ('(', ')')])
+            .unwrap()
+            .with_scores(&[('»', 10), (')', 1)], &[('»', 2), ('"', 3), (')', 1)])
+            .ignoring_other();
+        assert_eq!(quotes.diagnose("«say \"hi (there)\"»"), Ok(None));
+        let corrupted = quotes.diagnose("«a \"b»").unwrap().unwrap();
+        assert_eq!(
+            corrupted,
+            Diagnostic::Corrupted {
+                offset: 6,
+                found: '»',
+                expected: '"',
+            }
+        );
+        assert_eq!(quotes.score_corrupted(&corrupted), Some(10));
+        let incomplete = quotes.diagnose("(«\"").unwrap().unwrap();
+        assert_eq!(
+            incomplete,
+            Diagnostic::Incomplete {
+                completion: "\"»)".to_owned()
+            }
+        );
+        assert_eq!(
+            quotes.score_incomplete(&incomplete),
+            Some(3 * 25 + 2 * 5 + 1)
+        );
+        assert!(BracketSet::default().diagnose("(a)").is_err());
+        assert!(BracketSet::new(&[('(', ')'), (')', '(')]).is_err());
+    }
+
+    #[test]
+    fn test_check_reader() {
+        let brackets = BracketSet::default();
+        let (located, summary) = brackets.check_reader(INPUT.as_bytes(), false).unwrap();
+        assert_eq!(
+            summary,
+            Summary {
+                lines: 10,
+                corrupted: 5,
+                incomplete: 5,
+                median_completion: Some(288957),
+            }
+        );
+        assert_eq!(located[0].line, 1);
+        assert_eq!(located[0].column, 25);
+        assert_eq!(
+            located[2],
+            Located {
+                line: 3,
+                column: 13,
+                diagnostic: Diagnostic::Corrupted {
+                    offset: 12,
+                    found: '}',
+                    expected: ']',
+                },
+            }
+        );
+        let (located, summary) = brackets
+            .check_reader("{(\r\n[]\r\n)\n>\n<".as_bytes(), true)
+            .unwrap();
+        assert_eq!(summary.lines, 5);
+        assert_eq!((summary.corrupted, summary.incomplete), (1, 1));
+        assert_eq!((located[0].line, located[0].column), (4, 1));
+        assert_eq!((located[1].line, located[1].column), (5, 2));
+        assert!(brackets.check_reader("()\n(x)".as_bytes(), false).is_err());
+        // scores 1, 2, 3 and 4 have a lower middle of 2
+        let (_, summary) = brackets
+            .check_reader("(\n[\n{\n<".as_bytes(), false)
+            .unwrap();
+        assert_eq!(summary.incomplete, 4);
+        assert_eq!(summary.median_completion, Some(2));
+        // long nested lines whose completion score doesn't fit in a usize
+        let big = "([{<".repeat(1 << 16) + "\n";
+        let reader = std::io::Read::chain(big.as_bytes(), big.as_bytes());
+        let (_, summary) = brackets.check_reader(reader, true).unwrap();
+        assert_eq!((summary.lines, summary.incomplete), (2, 1));
+        assert_eq!(summary.median_completion, None);
+    }
+
+    fn edit_distance(a: &[char], b: &[char]) -> usize {
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for (i, ca) in a.iter().enumerate() {
+            let mut prev = row[0];
+            row[0] = i + 1;
+            for (j, cb) in b.iter().enumerate() {
+                let next = (prev + (ca != cb) as usize)
+                    .min(row[j] + 1)
+                    .min(row[j + 1] + 1);
+                prev = row[j + 1];
+                row[j + 1] = next;
+            }
+        }
+        row[b.len()]
+    }
+
+    fn balanced(pairs: usize, alphabet: &[(char, char)]) -> Vec<Vec<char>> {
+        if pairs == 0 {
+            return vec![vec![]];
+        }
+        let mut result = Vec::new();
+        for inner in 0..pairs {
+            for a in balanced(inner, alphabet) {
+                for b in balanced(pairs - 1 - inner, alphabet) {
+                    for &(open, close) in alphabet {
+                        let mut s = vec![open];
+                        s.extend(a.iter());
+                        s.push(close);
+                        s.extend(b.iter());
+                        result.push(s);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_repair() {
+        let brackets = BracketSet::default();
+        // substituting the second opener is as cheap as closing both
+        assert_eq!(brackets.repair("[(").unwrap().line, "[]");
+        let repair = brackets.repair("<{([").unwrap();
+        assert_eq!(repair.edits.len(), 2);
+        assert_eq!(repair.line, "<{}>");
+        let corrupted = brackets.repair("{([(<{}[<>[]}>{[]{[(<()>").unwrap();
+        assert_eq!(brackets.diagnose(&corrupted.line), Ok(None));
+        let repair = brackets.repair("(]").unwrap();
+        assert_eq!(
+            repair.edits,
+            vec![Edit::Substitute {
+                offset: 1,
+                from: ']',
+                to: ')'
+            }]
+        );
+        let repair = brackets.repair(">(<)").unwrap();
+        assert_eq!(repair.edits.len(), 2);
+        assert_eq!(brackets.diagnose(&repair.line), Ok(None));
+
+        // compare against the closest balanced string found by brute force
+        let alphabet = [('(', ')'), ('[', ']')];
+        let targets: Vec<Vec<char>> = (0..=4).flat_map(|p| balanced(p, &alphabet)).collect();
+        let chars = ['(', ')', '[', ']'];
+        for len in 0..=4 {
+            for mut n in 0..chars.len().pow(len) {
+                let line: String = (0..len)
+                    .map(|_| {
+                        let c = chars[n % chars.len()];
+                        n /= chars.len();
+                        c
+                    })
+                    .collect();
+                let line_chars: Vec<char> = line.chars().collect();
+                let best = targets
+                    .iter()
+                    .map(|t| edit_distance(&line_chars, t))
+                    .min()
+                    .unwrap();
+                let repair = brackets.repair(&line).unwrap();
+                assert_eq!(repair.edits.len(), best, "{}", line);
+                assert_eq!(brackets.diagnose(&repair.line), Ok(None), "{}", line);
+            }
+        }
+        let lenient = BracketSet::default().ignoring_other();
+        assert_eq!(lenient.repair("f(x[0)").unwrap().line, "f(x[0])");
+        assert!(brackets.repair("f(x)").is_err());
     }
 }
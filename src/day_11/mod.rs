@@ -1,77 +1,418 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet};
+
+const MOORE: [(isize, isize); 8] = [
+    (-1, -1), // ↖
+    (0, -1),  // ↑
+    (1, -1),  // ↗
+    (-1, 0),  // ←
+    (1, 0),   // →
+    (-1, 1),  // ↙
+    (0, 1),   // ↓
+    (1, 1),   // ↘
+];
+
+const VON_NEUMANN: [(isize, isize); 4] = [
+    (0, -1), // ↑
+    (-1, 0), // ←
+    (1, 0),  // →
+    (0, 1),  // ↓
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Neighborhood {
+    VonNeumann,
+    Moore,
+    // (dx, dy) offsets; (0, 0) is ignored
+    Custom(Vec<(isize, isize)>),
+}
+
+impl Neighborhood {
+    fn offsets(&self) -> &[(isize, isize)] {
+        match self {
+            Self::VonNeumann => &VON_NEUMANN,
+            Self::Moore => &MOORE,
+            Self::Custom(offsets) => offsets,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rules {
+    // an octopus flashes once its energy exceeds this
+    pub threshold: u8,
+    // energy an octopus is left with after flashing
+    pub reset: u8,
+    pub step_gain: u8,
+    // energy given to each neighbor of a flashing octopus
+    pub neighbor_gain: u8,
+    pub neighborhood: Neighborhood,
+    // steps after flashing during which an octopus neither gains energy nor flashes
+    pub refractory: u8,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            threshold: 9,
+            reset: 0,
+            step_gain: 1,
+            neighbor_gain: 1,
+            neighborhood: Neighborhood::Moore,
+            refractory: 0,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Octopi {
     // flattened data in reading order
     data: Vec<u8>,
+    // remaining refractory steps for each octopus
+    resting: Vec<u8>,
     // row width
     w: usize,
+    // whether neighbors wrap around the edges of the grid
+    wrap: bool,
+    rules: Rules,
 }
 
 impl Octopi {
     fn new<T: Into<u8>>(data: Vec<T>, w: usize) -> Self {
+        let data: Vec<u8> = data.into_iter().map(T::into).collect();
         Octopi {
-            data: data.into_iter().map(T::into).collect(),
-            w: w,
+            resting: vec![0; data.len()],
+            data,
+            w,
+            wrap: false,
+            rules: Rules::default(),
         }
     }
 
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    // Treat the grid as a torus, so octopi on one edge neighbor those on the opposite edge.
+    pub fn toroidal(mut self) -> Self {
+        self.wrap = true;
+        self
+    }
+
     fn neighbors(&self, i: usize) -> impl std::iter::IntoIterator<Item = usize> {
+        let (w, h) = (self.w as isize, (self.data.len() / self.w) as isize);
+        let (x, y) = ((i % self.w) as isize, (i / self.w) as isize);
         let mut result: Vec<usize> = Vec::new();
-        let x = i % self.w;
-        if i >= self.w && x > 0 {
-            result.push(i - self.w - 1); // ↖
+        for &(dx, dy) in self.rules.neighborhood.offsets() {
+            let (mut nx, mut ny) = (x + dx, y + dy);
+            if self.wrap {
+                nx = nx.rem_euclid(w);
+                ny = ny.rem_euclid(h);
+            } else if !(0..w).contains(&nx) || !(0..h).contains(&ny) {
+                continue;
+            }
+            // on narrow toroidal grids several offsets can land on the same octopus
+            let j = (ny * w + nx) as usize;
+            if j != i && !result.contains(&j) {
+                result.push(j);
+            }
         }
-        if i >= self.w {
-            result.push(i - self.w); // ↑
+        result.into_iter()
+    }
+
+    fn inc(&mut self, i: usize, gain: u8) -> bool {
+        if self.resting[i] > 0 {
+            return false;
         }
-        if i >= self.w && i % self.w < 9 {
-            result.push(i - self.w + 1); // ↗
+        self.data[i] = self.data[i].saturating_add(gain);
+        self.data[i] > self.rules.threshold
+    }
+
+    // Steps the grid, returning the octopi that flashed in each wave of the cascade: the first wave
+    // flashes from the step's own gain, and each later wave from the flashes of the one before.
+    pub fn step_waves(&mut self) -> Vec<Vec<usize>> {
+        let gain = self.rules.step_gain;
+        let mut wave: Vec<usize> = (0..self.data.len())
+            .filter(|&i| self.inc(i, gain))
+            .collect();
+        let mut already_flashed: HashSet<usize> = HashSet::from_iter(wave.iter().cloned());
+        let mut waves = Vec::new();
+        while !wave.is_empty() {
+            let mut next = Vec::new();
+            for &i in wave.iter() {
+                for j in self.neighbors(i) {
+                    if !already_flashed.contains(&j) && self.inc(j, self.rules.neighbor_gain) {
+                        next.push(j);
+                        already_flashed.insert(j);
+                    }
+                }
+            }
+            waves.push(wave);
+            wave = next;
         }
-        if x > 0 {
-            result.push(i - 1); // ←
+        for rest in self.resting.iter_mut() {
+            *rest = rest.saturating_sub(1);
         }
-        if i % self.w < 9 {
-            result.push(i + 1); // →
+        for &i in already_flashed.iter() {
+            self.data[i] = self.rules.reset;
+            self.resting[i] = self.rules.refractory;
         }
-        if i < self.data.len() - self.w && i % self.w > 0 {
-            result.push(i + self.w - 1) // ↙
+        waves
+    }
+
+    pub fn step(&mut self) -> usize {
+        self.step_waves().iter().map(|wave| wave.len()).sum()
+    }
+
+    // Runs a copy of the grid for `steps` steps, keeping every step's flashes.
+    pub fn record(&self, steps: usize) -> History {
+        let mut octopi = self.clone();
+        History {
+            initial: self.clone(),
+            steps: (0..steps).map(|_| octopi.step_waves()).collect(),
         }
-        if i < self.data.len() - self.w {
-            result.push(i + self.w); // ↓
+    }
+
+    // Steps a copy of the grid until it repeats a state it has been in before.
+    pub fn find_cycle(&self) -> Cycle {
+        let mut octopi = self.clone();
+        let mut flashes = Vec::new();
+        let mut seen: HashMap<(Vec<u8>, Vec<u8>), usize> = HashMap::new();
+        let state = |o: &Octopi| (o.data.clone(), o.resting.clone());
+        while !seen.contains_key(&state(&octopi)) {
+            seen.insert(state(&octopi), flashes.len());
+            flashes.push(octopi.step());
         }
-        if i < self.data.len() - self.w && i % self.w < 9 {
-            result.push(i + self.w + 1); // ↘
+        let start = seen[&state(&octopi)];
+        Cycle {
+            start,
+            length: flashes.len() - start,
+            flashes,
         }
-        result.into_iter()
     }
 
-    fn inc(&mut self, i: usize) -> bool {
-        if let Some(n) = self.data[i].checked_add(1) {
-            self.data[i] = n;
+    pub fn first_sync(&self) -> Option<usize> {
+        self.find_cycle().first_sync(self.data.len())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    // steps taken before the grid first enters the cycle
+    pub start: usize,
+    pub length: usize,
+    // flashes during each step up to the end of the first pass through the cycle
+    flashes: Vec<usize>,
+}
+
+impl Cycle {
+    fn flashes_through(&self, steps: usize) -> usize {
+        self.flashes[..steps].iter().sum()
+    }
+
+    // Total flashes over the first `steps` steps, for any number of steps.
+    pub fn flashes_after(&self, steps: usize) -> usize {
+        if steps <= self.flashes.len() {
+            return self.flashes_through(steps);
         }
-        self.data[i] > 9
+        let before = self.flashes_through(self.start);
+        let per_cycle = self.flashes_through(self.start + self.length) - before;
+        let (cycles, rest) = (
+            (steps - self.start) / self.length,
+            (steps - self.start) % self.length,
+        );
+        before + cycles * per_cycle + self.flashes_through(self.start + rest) - before
     }
 
-    fn step(&mut self) -> usize {
-        let mut flashed = VecDeque::from_iter((0..self.data.len()).filter(|&i| self.inc(i)));
-        let mut already_flashed: HashSet<usize> = HashSet::from_iter(flashed.iter().cloned());
-        while let Some(i) = flashed.pop_front() {
-            for j in self.neighbors(i) {
-                if !already_flashed.contains(&j) {
-                    if self.inc(j) {
-                        flashed.push_back(j);
-                        already_flashed.insert(j);
+    // The first step in which all `n` octopi flash, or None if the grid never synchronizes.
+    pub fn first_sync(&self, n: usize) -> Option<usize> {
+        self.flashes.iter().position(|&f| f == n).map(|i| i + 1)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct History {
+    initial: Octopi,
+    // for each step, the octopi that flashed in each wave of its cascade
+    pub steps: Vec<Vec<Vec<usize>>>,
+}
+
+const HISTORY_MAGIC: &[u8] = b"OCTO\x01";
+
+fn write_varint(bytes: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        bytes.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl ByteReader<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let b = *self
+            .bytes
+            .get(self.pos)
+            .ok_or("unexpected end of history")?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut n = 0;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            n |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err("varint too long".to_owned())
+    }
+
+    fn usize(&mut self) -> Result<usize, String> {
+        usize::try_from(self.varint()?).map_err(|e| e.to_string())
+    }
+
+    // zigzag-encoded, so small negative offsets stay small
+    fn isize(&mut self) -> Result<isize, String> {
+        let n = self.varint()?;
+        Ok(((n >> 1) as i64 ^ -((n & 1) as i64)) as isize)
+    }
+}
+
+impl History {
+    // Serializes to a compact binary form: a header, the grid, its rules and starting state,
+    // then each step's waves as varint-encoded cell indices.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let o = &self.initial;
+        let mut bytes = HISTORY_MAGIC.to_vec();
+        write_varint(&mut bytes, o.w as u64);
+        write_varint(&mut bytes, o.data.len() as u64);
+        bytes.push(o.wrap as u8);
+        let r = &o.rules;
+        bytes.extend([
+            r.threshold,
+            r.reset,
+            r.step_gain,
+            r.neighbor_gain,
+            r.refractory,
+        ]);
+        match &r.neighborhood {
+            Neighborhood::VonNeumann => bytes.push(0),
+            Neighborhood::Moore => bytes.push(1),
+            Neighborhood::Custom(offsets) => {
+                bytes.push(2);
+                write_varint(&mut bytes, offsets.len() as u64);
+                for &(dx, dy) in offsets {
+                    for d in [dx, dy] {
+                        write_varint(&mut bytes, ((d << 1) ^ (d >> (isize::BITS - 1))) as u64);
                     }
                 }
             }
         }
-        for i in 0..self.data.len() {
-            if self.data[i] > 9 {
-                self.data[i] = 0
+        bytes.extend(o.data.iter());
+        bytes.extend(o.resting.iter());
+        write_varint(&mut bytes, self.steps.len() as u64);
+        for waves in self.steps.iter() {
+            write_varint(&mut bytes, waves.len() as u64);
+            for wave in waves {
+                write_varint(&mut bytes, wave.len() as u64);
+                for &i in wave {
+                    write_varint(&mut bytes, i as u64);
+                }
             }
         }
-        already_flashed.len()
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.starts_with(HISTORY_MAGIC) {
+            return Err("not an octopus history".to_owned());
+        }
+        let mut r = ByteReader {
+            bytes,
+            pos: HISTORY_MAGIC.len(),
+        };
+        let (w, n) = (r.usize()?, r.usize()?);
+        if w == 0 || n % w != 0 {
+            return Err(format!("{} octopi don't fit rows of {}", n, w));
+        }
+        let wrap = r.byte()? != 0;
+        let mut rules = Rules {
+            threshold: r.byte()?,
+            reset: r.byte()?,
+            step_gain: r.byte()?,
+            neighbor_gain: r.byte()?,
+            refractory: r.byte()?,
+            ..Rules::default()
+        };
+        rules.neighborhood = match r.byte()? {
+            0 => Neighborhood::VonNeumann,
+            1 => Neighborhood::Moore,
+            2 => Neighborhood::Custom(
+                (0..r.usize()?)
+                    .map(|_| Ok((r.isize()?, r.isize()?)))
+                    .collect::<Result<Vec<_>, String>>()?,
+            ),
+            tag => return Err(format!("unknown neighborhood: {}", tag)),
+        };
+        let data = (0..n)
+            .map(|_| r.byte())
+            .collect::<Result<Vec<u8>, String>>()?;
+        let resting = (0..n)
+            .map(|_| r.byte())
+            .collect::<Result<Vec<u8>, String>>()?;
+        let mut steps = Vec::new();
+        for _ in 0..r.usize()? {
+            let mut waves = Vec::new();
+            for _ in 0..r.usize()? {
+                let wave = (0..r.usize()?)
+                    .map(|_| {
+                        r.usize().and_then(|i| {
+                            if i < n {
+                                Ok(i)
+                            } else {
+                                Err(format!("no octopus {}", i))
+                            }
+                        })
+                    })
+                    .collect::<Result<Vec<usize>, String>>()?;
+                waves.push(wave);
+            }
+            steps.push(waves);
+        }
+        if r.pos != bytes.len() {
+            return Err("trailing bytes after history".to_owned());
+        }
+        Ok(History {
+            initial: Octopi {
+                data,
+                resting,
+                w,
+                wrap,
+                rules,
+            },
+            steps,
+        })
+    }
+
+    // The grid after `step` steps, replayed from the start and checked against the recording.
+    pub fn state_at(&self, step: usize) -> Result<Octopi, String> {
+        if step > self.steps.len() {
+            return Err(format!("only {} steps recorded", self.steps.len()));
+        }
+        let mut octopi = self.initial.clone();
+        for (i, recorded) in self.steps[..step].iter().enumerate() {
+            if octopi.step_waves() != *recorded {
+                return Err(format!("step {} doesn't match the recording", i + 1));
+            }
+        }
+        Ok(octopi)
     }
 }
 
@@ -113,13 +454,7 @@ pub fn part_1(octopi: &Octopi) -> usize {
 
 #[aoc(day11, part2)]
 pub fn part_2(octopi: &Octopi) -> usize {
-    let mut octopi = octopi.clone();
-    let n = octopi.data.len();
-    let mut i: usize = 1;
-    while octopi.step() != n {
-        i += 1;
-    }
-    i
+    octopi.first_sync().unwrap()
 }
 
 #[cfg(test)]
@@ -139,4 +474,119 @@ mod tests {
     fn test_part_2() {
         assert_eq!(part_2(&get_input(INPUT)), 195);
     }
+
+    #[test]
+    fn test_any_width() {
+        let narrow = "1234567\n8912345\n6789123\n4567891\n2345678";
+        let wide = "12345678991234567899\n98765432159876543215\n55555555559999999999";
+        for (input, flat_10, flat_100, torus_10, torus_100) in
+            [(narrow, 68, 576, 70, 595), (wide, 122, 935, 126, 920)]
+        {
+            for (mut octopi, n_10, n_100) in [
+                (get_input(input), flat_10, flat_100),
+                (get_input(input).toroidal(), torus_10, torus_100),
+            ] {
+                assert_eq!((0..10).map(|_| octopi.step()).sum::<usize>(), n_10);
+                assert_eq!(
+                    (10..100).map(|_| octopi.step()).sum::<usize>(),
+                    n_100 - n_10
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cycle() {
+        let octopi = get_input(INPUT);
+        let cycle = octopi.find_cycle();
+        assert_eq!((cycle.start, cycle.length), (195, 10));
+        assert_eq!(cycle.flashes_after(100), 1656);
+        assert_eq!(cycle.flashes_after(1_000_000_000_000), 10_000_000_001_125);
+        // this grid settles into a cycle of seven steps without ever synchronizing
+        let octopi = get_input("1234567\n8912345\n6789123\n4567891\n2345678");
+        let cycle = octopi.find_cycle();
+        assert_eq!((cycle.start, cycle.length), (91, 7));
+        assert_eq!(octopi.first_sync(), None);
+        let mut stepped = octopi.clone();
+        assert_eq!(
+            cycle.flashes_after(1000),
+            (0..1000).map(|_| stepped.step()).sum::<usize>()
+        );
+        assert_eq!(cycle.flashes_after(1_000_000_000_000), 5_000_000_000_081);
+    }
+
+    #[test]
+    fn test_rules() {
+        // the puzzle rules spelled out match the defaults
+        let explicit = Rules {
+            threshold: 9,
+            reset: 0,
+            step_gain: 1,
+            neighbor_gain: 1,
+            neighborhood: Neighborhood::Custom(MOORE.to_vec()),
+            refractory: 0,
+        };
+        assert_eq!(part_1(&get_input(INPUT).with_rules(explicit)), 1656);
+        // a flash only reaches the orthogonal neighbors
+        let mut octopi = get_input("000\n090\n000").with_rules(Rules {
+            neighborhood: Neighborhood::VonNeumann,
+            neighbor_gain: 5,
+            ..Rules::default()
+        });
+        assert_eq!(octopi.step(), 1);
+        assert_eq!(octopi.to_string(), "161\n606\n161");
+        // with a refractory period of two, a lone octopus that always flashes rests in between
+        let mut octopi = get_input("9").with_rules(Rules {
+            reset: 9,
+            refractory: 2,
+            ..Rules::default()
+        });
+        assert_eq!(
+            (0..7).map(|_| octopi.step()).collect::<Vec<usize>>(),
+            vec![1, 0, 0, 1, 0, 0, 1]
+        );
+        let cycle = octopi.find_cycle();
+        assert_eq!(cycle.length, 3);
+        assert_eq!(cycle.flashes_after(3_000), 1_000);
+        // a knight's-move neighborhood
+        let mut octopi = get_input("9000\n0080\n0000").with_rules(Rules {
+            neighborhood: Neighborhood::Custom(vec![(2, 1), (-2, -1), (0, 0)]),
+            ..Rules::default()
+        });
+        assert_eq!(octopi.step(), 2);
+    }
+
+    #[test]
+    fn test_history() {
+        let mut octopi = get_input("11111\n19991\n19191\n19991\n11111");
+        let history = octopi.record(2);
+        assert_eq!(history.steps[0][0], vec![6, 7, 8, 11, 13, 16, 17, 18]);
+        assert_eq!(history.steps[0][1], vec![12]);
+        assert_eq!(history.steps[1], Vec::<Vec<usize>>::new());
+        octopi.step();
+        assert_eq!(history.state_at(1).unwrap().to_string(), octopi.to_string());
+
+        let octopi = get_input(INPUT).with_rules(Rules {
+            neighborhood: Neighborhood::Custom(vec![(-1, 0), (1, 0), (0, -2)]),
+            refractory: 1,
+            ..Rules::default()
+        });
+        let history = octopi.record(50);
+        let bytes = history.to_bytes();
+        let decoded = History::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.steps, history.steps);
+        let mut expected = octopi.clone();
+        (0..30).for_each(|_| {
+            expected.step();
+        });
+        assert_eq!(
+            decoded.state_at(30).unwrap().to_string(),
+            expected.to_string()
+        );
+        assert!(History::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut tampered = decoded.clone();
+        tampered.steps[3].push(vec![0]);
+        assert!(tampered.state_at(10).is_err());
+        assert!(tampered.state_at(51).is_err());
+    }
 }
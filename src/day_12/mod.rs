@@ -5,6 +5,124 @@ pub struct Graph {
     map: HashMap<String, usize>,
     adjacent: HashMap<usize, Vec<usize>>,
     small: HashSet<usize>,
+    // edges without an entry weigh 1
+    weights: HashMap<(usize, usize), usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct VisitPolicy {
+    pub start: String,
+    pub end: String,
+    // how often each small cave may be visited; big caves are unlimited
+    pub small_limit: usize,
+    // up to `.0` small caves may each be visited up to `.1` times instead
+    pub extra_visits: Option<(usize, usize)>,
+    // fixed limits for particular caves, which never get extra visits; start defaults to 1
+    pub limits: HashMap<String, usize>,
+    // moves that aren't allowed, from the first cave to the second
+    pub forbidden: HashSet<(String, String)>,
+    // the most the edges of a path may weigh in total
+    pub max_cost: Option<usize>,
+}
+
+impl VisitPolicy {
+    pub fn part_1() -> Self {
+        VisitPolicy {
+            start: "start".to_owned(),
+            end: "end".to_owned(),
+            small_limit: 1,
+            extra_visits: None,
+            limits: HashMap::new(),
+            forbidden: HashSet::new(),
+            max_cost: None,
+        }
+    }
+
+    pub fn part_2() -> Self {
+        VisitPolicy {
+            extra_visits: Some((1, 2)),
+            ..Self::part_1()
+        }
+    }
+}
+
+// A policy resolved against a graph's cave indices.
+struct Rules {
+    start: usize,
+    end: usize,
+    // None for unlimited caves
+    limits: Vec<Option<Limit>>,
+    // bits per cave in `Visits::counts`
+    width: u32,
+    extra_visits: Option<(usize, usize)>,
+    forbidden: HashSet<(usize, usize)>,
+    max_cost: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Limit {
+    max: usize,
+    // whether the cave can get extra visits
+    extendable: bool,
+    // where the cave's count sits in `Visits::counts`
+    shift: u32,
+}
+
+// paths to end from each cave and the visits made on the way there
+type Memo = HashMap<(usize, Visits), usize>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Visits {
+    // visits so far to each cave with a limit, packed `Rules::width` bits apiece
+    counts: u64,
+    // caves that have used extra visits
+    extras: usize,
+    // only tracked when there's a maximum cost, so it doesn't split the memo otherwise
+    cost: usize,
+}
+
+impl Rules {
+    fn visit(&self, graph: &Graph, visits: &Visits, i: usize, j: usize) -> Option<Visits> {
+        if self.forbidden.contains(&(i, j)) {
+            return None;
+        }
+        let mut next = *visits;
+        if let Some(max_cost) = self.max_cost {
+            next.cost += graph.weight(i, j);
+            if next.cost > max_cost {
+                return None;
+            }
+        }
+        if let Some(limit) = self.limits[j] {
+            let count = self.count(visits, limit) + 1;
+            if count > limit.max {
+                let (caves, times) = self.extra_visits.filter(|_| limit.extendable)?;
+                if count > times {
+                    return None;
+                }
+                if count == limit.max + 1 {
+                    if next.extras >= caves {
+                        return None;
+                    }
+                    next.extras += 1;
+                }
+            }
+            next.counts += 1 << limit.shift;
+        }
+        Some(next)
+    }
+
+    fn count(&self, visits: &Visits, limit: Limit) -> usize {
+        ((visits.counts >> limit.shift) & (u64::MAX >> (u64::BITS - self.width))) as usize
+    }
+
+    fn initial(&self) -> Visits {
+        Visits {
+            counts: self.limits[self.start].map_or(0, |limit| 1 << limit.shift),
+            extras: 0,
+            cost: 0,
+        }
+    }
 }
 
 impl Graph {
@@ -14,6 +132,7 @@ impl Graph {
             map: HashMap::new(),
             adjacent: HashMap::new(),
             small: HashSet::new(),
+            weights: HashMap::new(),
         }
     }
 
@@ -36,78 +155,347 @@ impl Graph {
         }
     }
 
-    fn add_edge(&mut self, name_a: &str, name_b: &str) -> () {
+    fn add_edge(&mut self, name_a: &str, name_b: &str, weight: usize) {
         let i_a = self.index(name_a).unwrap();
         let i_b = self.index(name_b).unwrap();
-        self.adjacent.entry(i_a).or_insert(Vec::new()).push(i_b);
-        self.adjacent.entry(i_b).or_insert(Vec::new()).push(i_a);
+        self.adjacent.entry(i_a).or_default().push(i_b);
+        self.adjacent.entry(i_b).or_default().push(i_a);
+        if weight != 1 {
+            self.weights.insert((i_a, i_b), weight);
+            self.weights.insert((i_b, i_a), weight);
+        }
+    }
+
+    fn neighbors(&self, i: usize) -> &[usize] {
+        self.adjacent.get(&i).map_or(&[], |v| v.as_slice())
+    }
+
+    fn weight(&self, i: usize, j: usize) -> usize {
+        self.weights.get(&(i, j)).cloned().unwrap_or(1)
+    }
+
+    fn resolve(&self, policy: &VisitPolicy) -> Result<Rules, String> {
+        let index = |name: &str| self.index(name).ok_or(format!("no cave named {}", name));
+        let start = index(&policy.start)?;
+        let mut limits: Vec<Option<(usize, bool)>> = (0..self.nodes.len())
+            .map(|i| {
+                self.small
+                    .contains(&i)
+                    .then_some((policy.small_limit, true))
+            })
+            .collect();
+        limits[start] = Some((1, false));
+        for (name, &limit) in policy.limits.iter() {
+            limits[index(name)?] = Some((limit, false));
+        }
+        // pack every count into a u64, with enough bits for the most visits any cave can get
+        let most = limits
+            .iter()
+            .flatten()
+            .map(|&(limit, extendable)| match policy.extra_visits {
+                Some((_, times)) if extendable => limit.max(times),
+                _ => limit,
+            })
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let width = usize::BITS - most.leading_zeros();
+        let slots = limits.iter().flatten().count() as u32;
+        if slots * width > u64::BITS {
+            return Err(format!(
+                "can't track {} caves with up to {} visits each",
+                slots, most
+            ));
+        }
+        let limits = limits
+            .into_iter()
+            .scan(0, |shift, limit| {
+                Some(limit.map(|(max, extendable)| {
+                    *shift += width;
+                    Limit {
+                        max,
+                        extendable,
+                        shift: *shift - width,
+                    }
+                }))
+            })
+            .collect();
+        Ok(Rules {
+            start,
+            end: index(&policy.end)?,
+            limits,
+            width,
+            extra_visits: policy.extra_visits,
+            forbidden: policy
+                .forbidden
+                .iter()
+                .map(|(a, b)| Ok((index(a)?, index(b)?)))
+                .collect::<Result<HashSet<(usize, usize)>, String>>()?,
+            max_cost: policy.max_cost,
+        })
+    }
+
+    // Pairs of adjacent big caves, each of which allows infinitely many paths.
+    pub fn big_cave_edges(&self) -> Vec<(&str, &str)> {
+        (0..self.nodes.len())
+            .filter(|i| !self.small.contains(i))
+            .flat_map(|i| {
+                self.neighbors(i)
+                    .iter()
+                    .filter(move |&&j| i <= j && !self.small.contains(&j))
+                    .map(move |&j| (self.nodes[i].as_str(), self.nodes[j].as_str()))
+            })
+            .collect()
+    }
+
+    // Groups of caves connected to each other, in order of their first cave's appearance.
+    pub fn components(&self) -> Vec<Vec<&str>> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut components = Vec::new();
+        for i in 0..self.nodes.len() {
+            if !seen[i] {
+                components.push(
+                    self.reachable(i, &mut seen)
+                        .into_iter()
+                        .map(|j| self.nodes[j].as_str())
+                        .collect(),
+                );
+            }
+        }
+        components
+    }
+
+    // Caves that can't be reached from `start` at all.
+    pub fn unreachable(&self, start: &str) -> Result<Vec<&str>, String> {
+        let start = self
+            .index(start)
+            .ok_or(format!("no cave named {}", start))?;
+        let mut seen = vec![false; self.nodes.len()];
+        self.reachable(start, &mut seen);
+        Ok((0..self.nodes.len())
+            .filter(|&i| !seen[i])
+            .map(|i| self.nodes[i].as_str())
+            .collect())
+    }
+
+    // Marks and returns the caves reachable from `i`, sorted.
+    fn reachable(&self, i: usize, seen: &mut [bool]) -> Vec<usize> {
+        let mut found = vec![i];
+        let mut stack = vec![i];
+        seen[i] = true;
+        while let Some(j) = stack.pop() {
+            for &k in self.neighbors(j) {
+                if !seen[k] {
+                    seen[k] = true;
+                    found.push(k);
+                    stack.push(k);
+                }
+            }
+        }
+        found.sort_unstable();
+        found
+    }
+
+    // Graphviz DOT, with small caves as circles and big caves as filled boxes.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph caves {\n");
+        for (i, name) in self.nodes.iter().enumerate() {
+            let style = if self.small.contains(&i) {
+                "shape=circle"
+            } else {
+                "shape=box, style=filled"
+            };
+            dot += &format!("    \"{}\" [{}];\n", name, style);
+        }
+        for i in 0..self.nodes.len() {
+            for &j in self.neighbors(i).iter().filter(|&&j| i <= j) {
+                let label = match self.weight(i, j) {
+                    1 => String::new(),
+                    w => format!(" [label={}]", w),
+                };
+                dot += &format!(
+                    "    \"{}\" -- \"{}\"{};\n",
+                    self.nodes[i], self.nodes[j], label
+                );
+            }
+        }
+        dot + "}\n"
     }
+
+    // Counts paths from start to end; errors if there are infinitely many.
+    pub fn count_paths(&self, policy: &VisitPolicy) -> Result<usize, String> {
+        let rules = self.resolve(policy)?;
+        Ok(self.search(&rules)?.0)
+    }
+
+    // The number of paths, and the memo of paths from every state the search reached.
+    fn search(&self, rules: &Rules) -> Result<(usize, Memo), String> {
+        let mut search = Search {
+            graph: self,
+            rules,
+            memo: HashMap::new(),
+            active: HashMap::new(),
+            path: Vec::new(),
+            loops: HashMap::new(),
+        };
+        let paths = search.count_from(rules.start, rules.initial())?;
+        Ok((paths, search.memo))
+    }
+
+    // Lazily enumerates paths from start to end, depth first.
+    pub fn paths(&self, policy: &VisitPolicy, filter: PathFilter) -> Result<Paths<'_>, String> {
+        let rules = self.resolve(policy)?;
+        let (_, memo) = self.search(&rules)?;
+        let stack = vec![(vec![rules.start], rules.initial())];
+        Ok(Paths {
+            graph: self,
+            must_visit: filter
+                .must_visit
+                .iter()
+                .map(|name| self.index(name).ok_or(format!("no cave named {}", name)))
+                .collect::<Result<Vec<usize>, String>>()?,
+            max_length: filter.max_length,
+            rules,
+            memo,
+            stack,
+        })
+    }
+}
+
+// The depth-first search behind counting, memoized on the cave and the visits made so far.
+struct Search<'a> {
+    graph: &'a Graph,
+    rules: &'a Rules,
+    memo: Memo,
+    // states on the current path, with their depth
+    active: HashMap<(usize, Visits), usize>,
+    path: Vec<usize>,
+    // caves on loops back to a state that's still active
+    loops: HashMap<(usize, Visits), Vec<usize>>,
+}
+
+impl Search<'_> {
+    // Coming back to an active state means a loop, which only matters if the state can go on
+    // to reach end: then the loop can be taken any number of times.
+    fn count_from(&mut self, i: usize, visits: Visits) -> Result<usize, String> {
+        if i == self.rules.end {
+            return Ok(1);
+        }
+        let state = (i, visits);
+        if let Some(&n) = self.memo.get(&state) {
+            return Ok(n);
+        }
+        if let Some(&depth) = self.active.get(&state) {
+            let caves = self.path[depth..].to_vec();
+            self.loops.entry(state).or_insert(caves);
+            return Ok(0);
+        }
+        self.active.insert(state, self.path.len());
+        self.path.push(i);
+        let mut paths = 0;
+        for &j in self.graph.neighbors(i) {
+            if let Some(next) = self.rules.visit(self.graph, &visits, i, j) {
+                paths += self.count_from(j, next)?;
+            }
+        }
+        self.path.pop();
+        self.active.remove(&state);
+        if let Some(mut caves) = self.loops.remove(&state).filter(|_| paths > 0) {
+            caves.sort_unstable();
+            caves.dedup();
+            let names: Vec<&str> = caves
+                .iter()
+                .map(|&c| self.graph.nodes[c].as_str())
+                .collect();
+            return Err(format!(
+                "infinitely many paths loop through {}",
+                names.join(", ")
+            ));
+        }
+        self.memo.insert(state, paths);
+        Ok(paths)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PathFilter {
+    // caves every path has to pass through
+    pub must_visit: Vec<String>,
+    // the most caves a path may contain, counting start and end
+    pub max_length: Option<usize>,
+}
+
+pub struct Paths<'a> {
+    graph: &'a Graph,
+    rules: Rules,
+    // paths from each state, so dead ends and loops that never reach end are skipped
+    memo: Memo,
+    // partial paths and the visits they've made
+    stack: Vec<(Vec<usize>, Visits)>,
+    must_visit: Vec<usize>,
+    max_length: Option<usize>,
+}
+
+impl<'a> Iterator for Paths<'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, visits)) = self.stack.pop() {
+            let i = *path.last().unwrap();
+            if i == self.rules.end {
+                if self.must_visit.iter().all(|j| path.contains(j)) {
+                    return Some(path.iter().map(|&j| self.graph.nodes[j].as_str()).collect());
+                }
+                continue;
+            }
+            if self.max_length.is_some_and(|max| path.len() >= max) {
+                continue;
+            }
+            for &j in self.graph.neighbors(i).iter().rev() {
+                let next = self.rules.visit(self.graph, &visits, i, j).filter(|&next| {
+                    j == self.rules.end || self.memo.get(&(j, next)).is_some_and(|&n| n > 0)
+                });
+                if let Some(next) = next {
+                    let mut next_path = path.clone();
+                    next_path.push(j);
+                    self.stack.push((next_path, next));
+                }
+            }
+        }
+        None
+    }
+}
+
+// Formats paths one per line, as in the puzzle: `start,A,b,A,end`.
+pub fn paths_to_text<'a, I: IntoIterator<Item = Vec<&'a str>>>(paths: I) -> String {
+    paths
+        .into_iter()
+        .map(|path| path.join(",") + "\n")
+        .collect()
 }
 
+// Edges are `a-b`, optionally weighted as `a-b:5`.
 #[aoc_generator(day12)]
-pub fn get_input<'a>(input: &str) -> Graph {
+pub fn get_input(input: &str) -> Graph {
     let mut graph = Graph::new();
     for line in input.lines() {
-        let (src, dst) = line.split_once("-").unwrap();
+        let (edge, weight) = line.split_once(":").unwrap_or((line, "1"));
+        let (src, dst) = edge.split_once("-").unwrap();
         graph.add_node(src);
         graph.add_node(dst);
-        graph.add_edge(src, dst);
+        graph.add_edge(src, dst, weight.parse().unwrap());
     }
     graph
 }
 
 #[aoc(day12, part1)]
 pub fn part_1(graph: &Graph) -> usize {
-    let start = graph.index("start").unwrap();
-    let mut queue: Vec<(usize, HashSet<usize>)> = vec![(start, HashSet::from([start]))];
-    let mut paths = 0;
-    while let Some((i, visited)) = queue.pop() {
-        if i == graph.map["end"] {
-            paths += 1;
-        } else {
-            for &j in graph.adjacent[&i].iter().filter(|j| !visited.contains(j)) {
-                if graph.small.contains(&j) {
-                    let mut visited_next = visited.clone();
-                    visited_next.insert(j);
-                    queue.push((j, visited_next));
-                } else {
-                    queue.push((j, visited.clone()));
-                }
-            }
-        }
-    }
-    paths
+    graph.count_paths(&VisitPolicy::part_1()).unwrap()
 }
 
 #[aoc(day12, part2)]
 pub fn part_2(graph: &Graph) -> usize {
-    let start = graph.index("start").unwrap();
-    let mut queue: Vec<(usize, HashSet<usize>, bool)> =
-        vec![(start, HashSet::from([start]), false)];
-    let mut paths = 0;
-    while let Some((i, visited, revisited)) = queue.pop() {
-        if i == graph.map["end"] {
-            paths += 1;
-        } else {
-            for &j in graph.adjacent[&i]
-                .iter()
-                .filter(|j| **j != graph.map["start"])
-            {
-                if !visited.contains(&j) {
-                    if graph.small.contains(&j) {
-                        let mut visited_next = visited.clone();
-                        visited_next.insert(j);
-                        queue.push((j, visited_next, revisited));
-                    } else {
-                        queue.push((j, visited.clone(), revisited));
-                    }
-                } else if !revisited {
-                    queue.push((j, visited.clone(), true))
-                }
-            }
-        }
-    }
-    paths
+    graph.count_paths(&VisitPolicy::part_2()).unwrap()
 }
 
 #[cfg(test)]
@@ -128,4 +516,168 @@ mod tests {
         assert_eq!(part_2(&get_input(INPUT_1)), 36);
         assert_eq!(part_2(&get_input(INPUT_2)), 103);
     }
+
+    #[test]
+    fn test_paths() {
+        let graph = get_input(INPUT_1);
+        let (part_1, part_2) = (VisitPolicy::part_1(), VisitPolicy::part_2());
+        let all = || PathFilter::default();
+        assert_eq!(graph.paths(&part_1, all()).unwrap().count(), 10);
+        assert_eq!(graph.paths(&part_2, all()).unwrap().count(), 36);
+        let text = paths_to_text(graph.paths(&part_1, all()).unwrap());
+        let mut lines: Vec<&str> = text.lines().collect();
+        lines.sort();
+        assert_eq!(lines[0], "start,A,b,A,c,A,end");
+        assert_eq!(lines[9], "start,b,end");
+        let through_c = PathFilter {
+            must_visit: vec!["c".to_owned()],
+            ..PathFilter::default()
+        };
+        assert_eq!(graph.paths(&part_1, through_c).unwrap().count(), 5);
+        let short = PathFilter {
+            max_length: Some(4),
+            ..PathFilter::default()
+        };
+        assert_eq!(
+            graph.paths(&part_1, short).unwrap().collect::<Vec<_>>(),
+            vec![
+                vec!["start", "A", "b", "end"],
+                vec!["start", "A", "end"],
+                vec!["start", "b", "A", "end"],
+                vec!["start", "b", "end"],
+            ]
+        );
+        let nowhere = PathFilter {
+            must_visit: vec!["nowhere".to_owned()],
+            ..PathFilter::default()
+        };
+        assert!(graph.paths(&part_1, nowhere).is_err());
+    }
+
+    #[test]
+    fn test_visit_policy() {
+        let graph = get_input(INPUT_2);
+        // enumeration and counting agree under every policy
+        let two_twice = VisitPolicy {
+            extra_visits: Some((2, 2)),
+            ..VisitPolicy::part_2()
+        };
+        let no_kj = VisitPolicy {
+            forbidden: HashSet::from([("kj".to_owned(), "HN".to_owned())]),
+            limits: HashMap::from([("dc".to_owned(), 3)]),
+            ..VisitPolicy::part_1()
+        };
+        let reversed = VisitPolicy {
+            start: "end".to_owned(),
+            end: "start".to_owned(),
+            ..VisitPolicy::part_1()
+        };
+        for policy in [&two_twice, &no_kj, &reversed] {
+            assert_eq!(
+                graph.count_paths(policy).unwrap(),
+                graph.paths(policy, PathFilter::default()).unwrap().count()
+            );
+        }
+        assert!(graph.count_paths(&two_twice).unwrap() > 103);
+        assert_eq!(graph.count_paths(&reversed), Ok(19));
+        let missing = VisitPolicy {
+            end: "exit".to_owned(),
+            ..VisitPolicy::part_1()
+        };
+        assert!(graph.count_paths(&missing).is_err());
+        let unlimited = VisitPolicy {
+            small_limit: usize::MAX,
+            ..VisitPolicy::part_1()
+        };
+        assert!(graph.count_paths(&unlimited).is_err());
+
+        // weighted edges bound the total cost of a path
+        let weighted = get_input("start-A:2\nstart-b\nA-b:5\nA-end:2\nb-end:4");
+        let cheap = VisitPolicy {
+            max_cost: Some(5),
+            ..VisitPolicy::part_1()
+        };
+        assert_eq!(graph.count_paths(&VisitPolicy::part_1()), Ok(19));
+        assert_eq!(weighted.count_paths(&VisitPolicy::part_1()), Ok(5));
+        assert_eq!(
+            paths_to_text(weighted.paths(&cheap, PathFilter::default()).unwrap()),
+            "start,A,end\nstart,b,end\n"
+        );
+    }
+
+    #[test]
+    fn test_structure() {
+        let graph = get_input(INPUT_1);
+        assert!(graph.big_cave_edges().is_empty());
+        assert_eq!(graph.components().len(), 1);
+        assert_eq!(graph.unreachable("start"), Ok(vec![]));
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("graph caves {\n"));
+        assert!(dot.contains("    \"A\" [shape=box, style=filled];\n"));
+        assert!(dot.contains("    \"b\" [shape=circle];\n"));
+        assert!(dot.contains("    \"start\" -- \"A\";\n"));
+        assert_eq!(dot.matches(" -- ").count(), 7);
+
+        let graph = get_input("start-A\nA-B\nB-end\nx-Y\nstart-c:3");
+        assert_eq!(graph.big_cave_edges(), vec![("A", "B")]);
+        assert_eq!(
+            graph.components(),
+            vec![vec!["start", "A", "B", "end", "c"], vec!["x", "Y"]]
+        );
+        assert_eq!(graph.unreachable("start"), Ok(vec!["x", "Y"]));
+        assert!(graph
+            .to_dot()
+            .contains("    \"start\" -- \"c\" [label=3];\n"));
+        assert_eq!(
+            graph.count_paths(&VisitPolicy::part_1()),
+            Err("infinitely many paths loop through A, B".to_owned())
+        );
+        assert!(graph
+            .paths(&VisitPolicy::part_2(), PathFilter::default())
+            .is_err());
+        // a one-way passage, a limit or a cost bound breaks the loop
+        let one_way = VisitPolicy {
+            forbidden: HashSet::from([("B".to_owned(), "A".to_owned())]),
+            ..VisitPolicy::part_1()
+        };
+        assert_eq!(graph.count_paths(&one_way), Ok(1));
+        let limited = VisitPolicy {
+            limits: HashMap::from([("A".to_owned(), 2)]),
+            ..VisitPolicy::part_1()
+        };
+        assert_eq!(graph.count_paths(&limited), Ok(2));
+        let bounded = VisitPolicy {
+            max_cost: Some(5),
+            ..VisitPolicy::part_1()
+        };
+        assert_eq!(graph.count_paths(&bounded), Ok(2));
+        // loops that no path from start to end can get into don't count
+        let graph = get_input("start-b\nb-end\nX-Y");
+        assert_eq!(part_1(&graph), 1);
+        let graph = get_input("start-b\nb-end\nend-X\nX-Y");
+        assert_eq!(part_1(&graph), 1);
+        let one_way = VisitPolicy {
+            forbidden: HashSet::from([("X".to_owned(), "b".to_owned())]),
+            ..VisitPolicy::part_1()
+        };
+        let graph = get_input("start-b\nb-end\nb-X\nX-Y");
+        assert_eq!(graph.count_paths(&one_way), Ok(1));
+        assert_eq!(part_1(&graph), 1);
+        assert_eq!(
+            paths_to_text(
+                graph
+                    .paths(&VisitPolicy::part_1(), PathFilter::default())
+                    .unwrap()
+            ),
+            "start,b,end\n"
+        );
+        // a second visit to b gets back out of the loop
+        assert_eq!(
+            graph.count_paths(&VisitPolicy::part_2()),
+            Err("infinitely many paths loop through X, Y".to_owned())
+        );
+        // nor can a loop be left through a small cave that's used up
+        let graph = get_input("start-a\na-B\nB-C\na-end");
+        assert_eq!(part_1(&graph), 1);
+    }
 }
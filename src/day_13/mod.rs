@@ -1,65 +1,442 @@
 use itertools::Itertools;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, VecDeque};
 
-#[derive(Clone)]
-enum Fold {
-    X(usize),
-    Y(usize),
+pub type Point = (isize, isize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+// Which way the far side of the sheet moves; the puzzle always folds up or left, toward lower
+// coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Lower,
+    Higher,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fold {
+    pub axis: Axis,
+    pub line: isize,
+    pub direction: Direction,
+}
+
+impl Fold {
+    pub fn new(axis: Axis, line: isize, direction: Direction) -> Self {
+        Fold {
+            axis,
+            line,
+            direction,
+        }
+    }
+
+    fn coordinate(&self, p: Point) -> isize {
+        match self.axis {
+            Axis::X => p.0,
+            Axis::Y => p.1,
+        }
+    }
+
+    fn mirror(&self, p: Point) -> Point {
+        match self.axis {
+            Axis::X => (2 * self.line - p.0, p.1),
+            Axis::Y => (p.0, 2 * self.line - p.1),
+        }
+    }
+
+    // Whether a point is on the side of the line that gets folded over.
+    fn moves(&self, p: Point) -> bool {
+        match self.direction {
+            Direction::Lower => self.coordinate(p) > self.line,
+            Direction::Higher => self.coordinate(p) < self.line,
+        }
+    }
+
+    pub fn apply(&self, p: Point) -> Point {
+        if self.moves(p) {
+            self.mirror(p)
+        } else {
+            p
+        }
+    }
 }
 
+// `fold along y=7`, optionally followed by the direction: `up` or `down` for y, `left` or
+// `right` for x.
 impl From<&str> for Fold {
     fn from(s: &str) -> Self {
-        let s = s.split_whitespace().next_back().unwrap();
-        match s.chars().nth(0).unwrap() {
-            'x' => Self::X(s[2..].parse().unwrap()),
-            'y' => Self::Y(s[2..].parse().unwrap()),
+        let mut words = s.split_whitespace().skip(2);
+        let (axis, line) = words.next().unwrap().split_once('=').unwrap();
+        let axis = match axis {
+            "x" => Axis::X,
+            "y" => Axis::Y,
             _ => panic!(),
-        }
+        };
+        let direction = match (axis, words.next()) {
+            (_, None) | (Axis::X, Some("left")) | (Axis::Y, Some("up")) => Direction::Lower,
+            (Axis::X, Some("right")) | (Axis::Y, Some("down")) => Direction::Higher,
+            _ => panic!(),
+        };
+        Fold::new(axis, line.parse().unwrap(), direction)
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Transparency {
-    points: BTreeSet<(usize, usize)>,
-    folds: Vec<Fold>,
+    points: BTreeSet<Point>,
+    // folds still to make, in order
+    folds: VecDeque<Fold>,
 }
 
 impl Transparency {
-    fn fold(&mut self) -> &Self {
-        match self.folds.pop() {
-            Some(Fold::X(x)) => {
-                self.points = self
-                    .points
-                    .iter()
-                    .map(|&p| if p.0 > x { (x - (p.0 - x), p.1) } else { p })
-                    .collect();
+    pub fn new<I: IntoIterator<Item = Fold>>(points: BTreeSet<Point>, folds: I) -> Self {
+        Transparency {
+            points,
+            folds: folds.into_iter().collect(),
+        }
+    }
+
+    pub fn points(&self) -> &BTreeSet<Point> {
+        &self.points
+    }
+
+    pub fn pending(&self) -> &VecDeque<Fold> {
+        &self.folds
+    }
+
+    // Makes the next pending fold, if there is one.
+    pub fn fold(&mut self) -> &Self {
+        if let Some(fold) = self.folds.pop_front() {
+            self.apply(fold);
+        }
+        self
+    }
+
+    // Folds along any line, pending or not.
+    pub fn apply(&mut self, fold: Fold) {
+        self.points = self.points.iter().map(|&p| fold.apply(p)).collect();
+    }
+
+    // The sheet as it is, then after each pending fold in turn.
+    pub fn steps(&self) -> impl Iterator<Item = Transparency> {
+        std::iter::successors(Some(self.clone()), |sheet| {
+            let mut sheet = sheet.clone();
+            sheet.folds.pop_front().map(|fold| {
+                sheet.apply(fold);
+                sheet
+            })
+        })
+    }
+
+    // Every sheet that folding along `fold` turns into this one, with `fold` pending first.
+    // Each point off the line could have started out where it is, mirrored, or in both places.
+    pub fn unfold(&self, fold: Fold) -> Result<Unfoldings, String> {
+        if let Some(p) = self.points.iter().find(|&&p| fold.moves(p)) {
+            return Err(format!("({}, {}) is on the folded side", p.0, p.1));
+        }
+        let (fixed, movable): (Vec<Point>, Vec<Point>) = self
+            .points
+            .iter()
+            .partition(|&&p| fold.coordinate(p) == fold.line);
+        let mut folds = self.folds.clone();
+        folds.push_front(fold);
+        Ok(Unfoldings {
+            fold,
+            choices: Some(vec![0; movable.len()]),
+            fixed,
+            movable,
+            folds,
+        })
+    }
+
+    // The corner everything is drawn from: the origin, unless there are points above or left
+    // of it.
+    fn origin(&self) -> Point {
+        let x_min = self.points.iter().map(|p| p.0).min().unwrap_or(0);
+        let y_min = self.points.iter().map(|p| p.1).min().unwrap_or(0);
+        (x_min.min(0), y_min.min(0))
+    }
+
+    fn render(&self) -> String {
+        let (x_0, y_0) = self.origin();
+        let x_max = self.points.iter().map(|p| p.0).max().unwrap() + 1;
+        let y_max = self.points.iter().map(|p| p.1).max().unwrap() + 1;
+        let mut printout = vec![vec![" "; (x_max - x_0) as usize]; (y_max - y_0) as usize];
+        for p in self.points.iter() {
+            printout[(p.1 - y_0) as usize][(p.0 - x_0) as usize] = "█";
+        }
+        "\n".to_owned() + printout.iter().map(|l| l.join("")).join("\n").as_str()
+    }
+
+    // Top left and bottom right corners in cells, the latter exclusive, taking in the pending
+    // fold lines when they're drawn too.
+    fn bounds(&self, folds: bool) -> (Point, Point) {
+        let (mut x_0, mut y_0) = self.origin();
+        let mut x_1 = self.points.iter().map(|p| p.0 + 1).max().unwrap_or(0);
+        let mut y_1 = self.points.iter().map(|p| p.1 + 1).max().unwrap_or(0);
+        for fold in self.folds.iter().filter(|_| folds) {
+            let (min, max) = match fold.axis {
+                Axis::X => (&mut x_0, &mut x_1),
+                Axis::Y => (&mut y_0, &mut y_1),
+            };
+            *min = (*min).min(fold.line);
+            *max = (*max).max(fold.line + 1);
+        }
+        ((x_0, y_0), (x_1, y_1))
+    }
+
+    // Plain PBM, with fold lines dashed through every other cell.
+    pub fn to_pbm(&self, options: &ImageOptions) -> String {
+        let image = Image::new(self, options);
+        let mut pixels = vec![vec![false; image.width]; image.height];
+        let mut fill = |p: Point| {
+            let (x, y) = image.corner(p);
+            for row in pixels[y..y + options.scale].iter_mut() {
+                row[x..x + options.scale].fill(true);
             }
-            Some(Fold::Y(y)) => {
-                self.points = self
-                    .points
-                    .iter()
-                    .map(|&p| if p.1 > y { (p.0, y - (p.1 - y)) } else { p })
-                    .collect();
+        };
+        for fold in self.folds.iter().filter(|_| options.show_folds) {
+            let ((x_0, y_0), (x_1, y_1)) = image.bounds;
+            match fold.axis {
+                Axis::X => (y_0..y_1).step_by(2).for_each(|y| fill((fold.line, y))),
+                Axis::Y => (x_0..x_1).step_by(2).for_each(|x| fill((x, fold.line))),
             }
-            None => {}
         }
-        self
+        for &p in self.points.iter() {
+            fill(p);
+        }
+        let mut pbm = format!("P1\n{} {}\n", image.width, image.height);
+        for row in pixels {
+            pbm.extend(row.iter().map(|&on| if on { '1' } else { '0' }));
+            pbm.push('\n');
+        }
+        pbm
+    }
+
+    // SVG with black squares for points and dashed red lines through the middle of fold lines.
+    pub fn to_svg(&self, options: &ImageOptions) -> String {
+        let image = Image::new(self, options);
+        let scale = options.scale;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+            image.width, image.height
+        );
+        svg += "  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n";
+        for &p in self.points.iter() {
+            let (x, y) = image.corner(p);
+            svg += &format!(
+                "  <rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"black\"/>\n",
+                x, y, scale
+            );
+        }
+        let (m, half) = (options.margin as f64, scale as f64 / 2.0);
+        for fold in self.folds.iter().filter(|_| options.show_folds) {
+            let at = image.offset(fold.axis, fold.line) as f64 + half;
+            let ((x_1, y_1), (x_2, y_2)) = match fold.axis {
+                Axis::X => ((at, m), (at, image.height as f64 - m)),
+                Axis::Y => ((m, at), (image.width as f64 - m, at)),
+            };
+            svg += &format!(
+                "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"red\" stroke-dasharray=\"{}\"/>\n",
+                x_1, y_1, x_2, y_2, scale
+            );
+        }
+        svg + "</svg>\n"
+    }
+
+    // Reads the points as capital letters in the puzzle's font, each 4 wide and 6 tall with a
+    // blank column after it, starting from the origin.
+    pub fn read(&self) -> Result<String, UnknownGlyphs> {
+        let (x_0, y_0) = self.origin();
+        let points: Vec<(usize, usize)> = self
+            .points
+            .iter()
+            .map(|p| ((p.0 - x_0) as usize, (p.1 - y_0) as usize))
+            .collect();
+        let n = points.iter().map(|p| p.0 / 5 + 1).max().unwrap_or(0);
+        let mut glyphs = vec![0u32; n];
+        let mut misplaced = vec![false; n];
+        for (x, y) in points {
+            if x % 5 == 4 || y >= 6 {
+                misplaced[x / 5] = true;
+            } else {
+                glyphs[x / 5] |= 1 << (y * 4 + x % 5);
+            }
+        }
+        let mut text = String::new();
+        let mut positions = Vec::new();
+        for (i, glyph) in glyphs.into_iter().enumerate() {
+            match FONT.iter().find(|(_, g)| *g == glyph && !misplaced[i]) {
+                Some(&(c, _)) => text.push(c),
+                None => {
+                    text.push('?');
+                    positions.push(i);
+                }
+            }
+        }
+        if positions.is_empty() {
+            Ok(text)
+        } else {
+            Err(UnknownGlyphs { text, positions })
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ImageOptions {
+    // pixels per cell
+    pub scale: usize,
+    // blank pixels around the edge
+    pub margin: usize,
+    pub show_folds: bool,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        ImageOptions {
+            scale: 4,
+            margin: 4,
+            show_folds: false,
+        }
+    }
+}
+
+// Where a sheet's cells land in an image.
+struct Image {
+    bounds: (Point, Point),
+    scale: usize,
+    margin: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Image {
+    fn new(sheet: &Transparency, options: &ImageOptions) -> Self {
+        let bounds = sheet.bounds(options.show_folds);
+        let ((x_0, y_0), (x_1, y_1)) = bounds;
+        let size = |cells: isize| cells as usize * options.scale + 2 * options.margin;
+        Image {
+            bounds,
+            scale: options.scale,
+            margin: options.margin,
+            width: size(x_1 - x_0),
+            height: size(y_1 - y_0),
+        }
+    }
+
+    // The first pixel of row or column `line` along an axis.
+    fn offset(&self, axis: Axis, line: isize) -> usize {
+        let start = match axis {
+            Axis::X => self.bounds.0 .0,
+            Axis::Y => self.bounds.0 .1,
+        };
+        self.margin + (line - start) as usize * self.scale
+    }
+
+    // The top left pixel of a cell.
+    fn corner(&self, p: Point) -> (usize, usize) {
+        (self.offset(Axis::X, p.0), self.offset(Axis::Y, p.1))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UnknownGlyphs {
+    // the recognized text, with `?` for each unknown glyph
+    pub text: String,
+    // indices of the unknown glyphs, counting from the left
+    pub positions: Vec<usize>,
+}
+
+pub struct Unfoldings {
+    fold: Fold,
+    // points on the fold line, which stay put
+    fixed: Vec<Point>,
+    movable: Vec<Point>,
+    // for each movable point: 0 to stay, 1 to mirror, 2 for both; None once exhausted
+    choices: Option<Vec<u8>>,
+    folds: VecDeque<Fold>,
+}
+
+impl Iterator for Unfoldings {
+    type Item = Transparency;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let choices = self.choices.as_mut()?;
+        let mut points: BTreeSet<Point> = self.fixed.iter().cloned().collect();
+        for (&p, &choice) in self.movable.iter().zip(choices.iter()) {
+            if choice != 1 {
+                points.insert(p);
+            }
+            if choice != 0 {
+                points.insert(self.fold.mirror(p));
+            }
+        }
+        // count up in base 3
+        match choices.iter().position(|&c| c < 2) {
+            Some(i) => {
+                choices[..i].fill(0);
+                choices[i] += 1;
+            }
+            None => self.choices = None,
+        }
+        Some(Transparency {
+            points,
+            folds: self.folds.clone(),
+        })
+    }
+}
+
+// Glyphs as bitmasks, row by row from the top with the leftmost pixel lowest.
+const fn glyph(rows: [&str; 6]) -> u32 {
+    let mut mask = 0;
+    let mut y = 0;
+    while y < 6 {
+        let row = rows[y].as_bytes();
+        let mut x = 0;
+        while x < 4 {
+            if row[x] == b'#' {
+                mask |= 1 << (y * 4 + x);
+            }
+            x += 1;
+        }
+        y += 1;
     }
+    mask
 }
 
+const FONT: [(char, u32); 17] = [
+    ('A', glyph([".##.", "#..#", "#..#", "####", "#..#", "#..#"])),
+    ('B', glyph(["###.", "#..#", "###.", "#..#", "#..#", "###."])),
+    ('C', glyph([".##.", "#..#", "#...", "#...", "#..#", ".##."])),
+    ('E', glyph(["####", "#...", "###.", "#...", "#...", "####"])),
+    ('F', glyph(["####", "#...", "###.", "#...", "#...", "#..."])),
+    ('G', glyph([".##.", "#..#", "#...", "#.##", "#..#", ".###"])),
+    ('H', glyph(["#..#", "#..#", "####", "#..#", "#..#", "#..#"])),
+    ('I', glyph([".###", "..#.", "..#.", "..#.", "..#.", ".###"])),
+    ('J', glyph(["..##", "...#", "...#", "...#", "#..#", ".##."])),
+    ('K', glyph(["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"])),
+    ('L', glyph(["#...", "#...", "#...", "#...", "#...", "####"])),
+    ('O', glyph([".##.", "#..#", "#..#", "#..#", "#..#", ".##."])),
+    ('P', glyph(["###.", "#..#", "#..#", "###.", "#...", "#..."])),
+    ('R', glyph(["###.", "#..#", "#..#", "###.", "#.#.", "#..#"])),
+    ('S', glyph([".###", "#...", "#...", ".##.", "...#", "###."])),
+    ('U', glyph(["#..#", "#..#", "#..#", "#..#", "#..#", ".##."])),
+    ('Z', glyph(["####", "...#", "..#.", ".#..", "#...", "####"])),
+];
+
 #[aoc_generator(day13)]
 pub fn get_input(input: &str) -> Transparency {
     let (points_str, folds_str) = input.split_once("\n\n").unwrap();
-    let points: BTreeSet<(usize, usize)> = points_str
+    let points: BTreeSet<Point> = points_str
         .lines()
         .map(|l| l.split_once(",").unwrap())
         .map(|(a, b)| (a.parse().unwrap(), b.parse().unwrap()))
         .collect();
-    let folds = folds_str.lines().map(|l| l.into()).rev().collect();
-    Transparency {
-        points: points,
-        folds: folds,
-    }
+    let folds = folds_str.lines().map(|l| l.into());
+    Transparency::new(points, folds)
 }
 
 #[aoc(day13, part1)]
@@ -69,19 +446,16 @@ pub fn part_1(transparency: &Transparency) -> usize {
     transparency.points.len()
 }
 
+// The letters if they can be read, otherwise the points drawn out.
 #[aoc(day13, part2)]
 pub fn part_2(transparency: &Transparency) -> String {
     let mut transparency = transparency.clone();
     while !transparency.folds.is_empty() {
         transparency.fold();
     }
-    let x_max = transparency.points.iter().map(|p| p.0).max().unwrap() + 1;
-    let y_max = transparency.points.iter().map(|p| p.1).max().unwrap() + 1;
-    let mut printout = vec![vec![" "; x_max]; y_max];
-    for p in transparency.points {
-        printout[p.1][p.0] = "█";
-    }
-    "\n".to_owned() + printout.iter().map(|l| l.join("")).join("\n").as_str()
+    transparency
+        .read()
+        .unwrap_or_else(|_| transparency.render())
 }
 
 #[cfg(test)]
@@ -89,6 +463,7 @@ mod tests {
     use super::*;
 
     const INPUT: &'static str = include_str!("../../test_data/day_13.txt");
+    const INPUT_2: &'static str = include_str!("../../test_data/day_13_2.txt");
 
     #[test]
     fn test_part_1() {
@@ -96,5 +471,140 @@ mod tests {
     }
 
     #[test]
-    fn test_part_2() {}
+    fn test_part_2() {
+        assert_eq!(
+            part_2(&get_input(INPUT)),
+            "\n█████\n█   █\n█   █\n█   █\n█████"
+        );
+        assert_eq!(part_2(&get_input(INPUT_2)), "BLOCKERS");
+    }
+
+    #[test]
+    fn test_read() {
+        let mut transparency = get_input(INPUT_2);
+        transparency.fold();
+        transparency.fold();
+        // smudge the second glyph and put a point in the gap after the fourth
+        transparency.points.insert((7, 0));
+        transparency.points.insert((19, 2));
+        assert_eq!(
+            transparency.read(),
+            Err(UnknownGlyphs {
+                text: "B?O?KERS".to_owned(),
+                positions: vec![1, 3],
+            })
+        );
+    }
+
+    #[test]
+    fn test_fold() {
+        // folding left of centre reflects past zero
+        let points: BTreeSet<Point> = [(0, 0), (10, 1), (1, 2)].into_iter().collect();
+        let mut sheet = Transparency::new(points.clone(), [Fold::from("fold along x=2")]);
+        sheet.fold();
+        assert_eq!(
+            sheet.points,
+            [(0, 0), (-6, 1), (1, 2)].into_iter().collect()
+        );
+        let mut sheet = Transparency::new(points, [Fold::from("fold along x=2 right")]);
+        sheet.fold();
+        assert_eq!(
+            sheet.points,
+            [(4, 0), (10, 1), (3, 2)].into_iter().collect()
+        );
+        assert_eq!(
+            Fold::from("fold along y=-3 down"),
+            Fold::new(Axis::Y, -3, Direction::Higher)
+        );
+
+        let sheet = get_input(INPUT);
+        let steps: Vec<Transparency> = sheet.steps().collect();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0], sheet);
+        assert_eq!(steps[1].points.len(), 17);
+        assert_eq!(steps[2].points.len(), 16);
+        assert!(steps[2].pending().is_empty());
+    }
+
+    #[test]
+    fn test_images() {
+        let points: BTreeSet<Point> = [(0, 0), (2, 1)].into_iter().collect();
+        let sheet = Transparency::new(points, [Fold::from("fold along y=3")]);
+        let plain = ImageOptions {
+            scale: 2,
+            margin: 1,
+            show_folds: false,
+        };
+        assert_eq!(
+            sheet.to_pbm(&plain),
+            "P1\n8 6\n00000000\n01100000\n01100000\n00000110\n00000110\n00000000\n"
+        );
+        let folds = ImageOptions {
+            scale: 1,
+            margin: 0,
+            show_folds: true,
+        };
+        assert_eq!(sheet.to_pbm(&folds), "P1\n3 4\n100\n001\n000\n101\n");
+
+        let svg = sheet.to_svg(&folds);
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"3\" height=\"4\"")
+        );
+        assert_eq!(svg.matches("fill=\"black\"").count(), 2);
+        assert!(svg.contains("<rect x=\"2\" y=\"1\" width=\"1\" height=\"1\" fill=\"black\"/>"));
+        assert!(svg.contains("<line x1=\"0\" y1=\"3.5\" x2=\"3\" y2=\"3.5\""));
+        assert!(!sheet.to_svg(&plain).contains("<line"));
+        assert!(svg.ends_with("</svg>\n"));
+
+        // a fold line left of the points, on the other axis from the points' extent
+        let sheet = Transparency::new(sheet.points.clone(), [Fold::from("fold along x=-3 right")]);
+        let options = ImageOptions {
+            scale: 2,
+            margin: 0,
+            show_folds: true,
+        };
+        let svg = sheet.to_svg(&options);
+        assert!(svg.contains("<line x1=\"1\" y1=\"0\" x2=\"1\" y2=\"4\""));
+        assert!(svg.contains("<rect x=\"6\" y=\"0\" width=\"2\" height=\"2\" fill=\"black\"/>"));
+        assert_eq!(sheet.to_pbm(&options).lines().nth(1), Some("12 4"));
+    }
+
+    #[test]
+    fn test_unfold() {
+        let points: BTreeSet<Point> = [(0, 0), (1, 5)].into_iter().collect();
+        let sheet = Transparency::new(points, []);
+        let fold = Fold::new(Axis::Y, 5, Direction::Lower);
+        let unfolded: Vec<BTreeSet<Point>> = sheet
+            .unfold(fold)
+            .unwrap()
+            .map(|sheet| sheet.points)
+            .collect();
+        assert_eq!(
+            unfolded,
+            vec![
+                [(0, 0), (1, 5)].into_iter().collect(),
+                [(0, 10), (1, 5)].into_iter().collect(),
+                [(0, 0), (0, 10), (1, 5)].into_iter().collect(),
+            ]
+        );
+        assert!(sheet
+            .unfold(Fold::new(Axis::X, 0, Direction::Higher))
+            .is_ok());
+        assert!(sheet
+            .unfold(Fold::new(Axis::X, 0, Direction::Lower))
+            .is_err());
+
+        // each pre-image folds back into the original
+        let mut last = get_input(INPUT).steps().last().unwrap();
+        last.points.retain(|p| p.1 == 0);
+        let fold = Fold::from("fold along x=5");
+        let mut count = 0;
+        for mut sheet in last.unfold(fold).unwrap() {
+            assert_eq!(sheet.pending(), &VecDeque::from([fold]));
+            sheet.fold();
+            assert_eq!(sheet, last);
+            count += 1;
+        }
+        assert_eq!(count, 3usize.pow(5));
+    }
 }
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use num::{BigUint, One, Zero};
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Clone)]
 pub struct Polymer {
@@ -35,6 +36,85 @@ impl Polymer {
         (b == self.last).then(|| n_b += 1);
         n_a - n_b
     }
+
+    // Every pair that can turn up, numbered in sorted order.
+    fn pair_indices(&self) -> HashMap<(u8, u8), usize> {
+        let mut pairs: Vec<(u8, u8)> = self.pairs.keys().cloned().collect();
+        for (&(left, right), &middle) in self.rules.iter() {
+            pairs.extend([(left, right), (left, middle), (middle, right)]);
+        }
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs.into_iter().enumerate().map(|(i, p)| (p, i)).collect()
+    }
+
+    // How many of each element there are after `steps` steps, found by raising the pair
+    // transition matrix to the power of `steps` by repeated squaring. Pairs without a rule stay
+    // as they are.
+    pub fn histogram(&self, steps: u64) -> BTreeMap<char, BigUint> {
+        let indices = self.pair_indices();
+        let n = indices.len();
+        let mut transitions = vec![vec![BigUint::zero(); n]; n];
+        for (&(left, right), &i) in indices.iter() {
+            match self.rules.get(&(left, right)) {
+                Some(&middle) => {
+                    transitions[i][indices[&(left, middle)]] += 1u32;
+                    transitions[i][indices[&(middle, right)]] += 1u32;
+                }
+                None => transitions[i][i] += 1u32,
+            }
+        }
+        let mut counts = vec![BigUint::zero(); n];
+        for (pair, &k) in self.pairs.iter() {
+            counts[indices[pair]] = BigUint::from(k);
+        }
+        let mut steps = steps;
+        while steps > 0 {
+            if steps % 2 == 1 {
+                counts = multiply_vector(&counts, &transitions);
+            }
+            steps /= 2;
+            if steps > 0 {
+                transitions = multiply(&transitions, &transitions);
+            }
+        }
+        // every element but the last starts exactly one pair
+        let mut histogram = BTreeMap::new();
+        histogram.insert(self.last as char, BigUint::one());
+        for (&(left, _), &i) in indices.iter() {
+            *histogram.entry(left as char).or_insert_with(BigUint::zero) += &counts[i];
+        }
+        histogram.retain(|_, n| !n.is_zero());
+        histogram
+    }
+
+    pub fn length(&self, steps: u64) -> BigUint {
+        self.histogram(steps).into_values().sum()
+    }
+
+    // The most common element's count minus the least common's.
+    pub fn spread(&self, steps: u64) -> BigUint {
+        let histogram = self.histogram(steps);
+        let max = histogram.values().max().unwrap();
+        let min = histogram.values().min().unwrap();
+        max - min
+    }
+}
+
+fn multiply(a: &[Vec<BigUint>], b: &[Vec<BigUint>]) -> Vec<Vec<BigUint>> {
+    a.iter().map(|row| multiply_vector(row, b)).collect()
+}
+
+fn multiply_vector(v: &[BigUint], m: &[Vec<BigUint>]) -> Vec<BigUint> {
+    let mut product = vec![BigUint::zero(); m.len()];
+    for (x, row) in v.iter().zip(m.iter()).filter(|(x, _)| !x.is_zero()) {
+        for (p, y) in product.iter_mut().zip(row.iter()) {
+            if !y.is_zero() {
+                *p += x * y;
+            }
+        }
+    }
+    product
 }
 
 impl From<&str> for Polymer {
@@ -97,4 +177,32 @@ mod tests {
     fn test_part_2() {
         assert_eq!(part_2(&get_input(INPUT)), 2188189693529);
     }
+
+    #[test]
+    fn test_histogram() {
+        let polymer = get_input(INPUT);
+        let counts = |steps| {
+            polymer
+                .histogram(steps)
+                .into_iter()
+                .map(|(c, n)| (c, n.try_into().unwrap()))
+                .collect::<Vec<(char, u64)>>()
+        };
+        assert_eq!(counts(0), vec![('B', 1), ('C', 1), ('N', 2)]);
+        assert_eq!(
+            counts(10),
+            vec![('B', 1749), ('C', 298), ('H', 161), ('N', 865)]
+        );
+        assert_eq!(polymer.length(10), BigUint::from(3073u32));
+        assert_eq!(polymer.spread(10), BigUint::from(1588u32));
+        assert_eq!(polymer.spread(40), BigUint::from(2188189693529u64));
+        // agrees with stepping one at a time
+        let mut stepped = polymer.clone();
+        (0..20).for_each(|_| stepped.step());
+        assert_eq!(polymer.spread(20), BigUint::from(stepped.result()));
+        // three pairs double every step
+        let length: BigUint = (BigUint::from(3u32) << 1000) + 1u32;
+        assert_eq!(polymer.length(1000), length);
+        assert!(polymer.histogram(1000)[&'N'] > BigUint::zero());
+    }
 }